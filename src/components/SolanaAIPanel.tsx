@@ -1,6 +1,6 @@
 import React, { useState, useEffect } from 'react';
 import { PublicKey, Connection } from '@solana/web3.js';
-import { BiometricNFTClient, createAnchorProvider } from '../utils/solana-client';
+import { BiometricNFTClient, createAnchorProvider, unixSeconds } from '../utils/solana-client';
 import { HybridAIManager } from '../utils/hybrid-ai-manager';
 import { 
   Image, 
@@ -299,7 +299,7 @@ export const SolanaAIPanel: React.FC<SolanaAIPanelProps> = ({
           valence: biometricData.emotions[0]?.valence || 0.5,
           arousal: biometricData.emotions[0]?.arousal || 0.5,
           dominance: biometricData.emotions[0]?.dominance || 0.5,
-          timestamp: Date.now()
+          timestamp: unixSeconds()
         },
         eegHash.confidence,
         aiBiometricHash
@@ -6,7 +6,7 @@ import { ConnectionProvider, WalletProvider, useWallet, useConnection } from '@s
 import { PhantomWalletAdapter, SolflareWalletAdapter, TorusWalletAdapter } from '@solana/wallet-adapter-wallets';
 import { toast } from 'sonner';
 import '@solana/wallet-adapter-react-ui/styles.css';
-import BiometricNFTClient, { createAnchorProvider } from '../utils/solana-client';
+import BiometricNFTClient, { createAnchorProvider, unixSeconds } from '../utils/solana-client';
 import MediaPipeSensors from '../components/MediaPipeSensors';
 import LeapMotionSensors from '../components/LeapMotionSensors';
 import { RealBiometricCapture } from '../components/RealBiometricCapture';
@@ -540,7 +540,7 @@ const SolanaEmotionalNFT: React.FC = () => {
           valence: emotionData.valence,
           arousal: emotionData.arousal,
           dominance: emotionData.dominance,
-          timestamp: Date.now()
+          timestamp: unixSeconds()
         },
         qualityScore,
         emotionData.biometricHash
@@ -2,10 +2,15 @@
 // Production-ready implementation with proper error handling
 
 use anchor_lang::prelude::*;
-use anchor_lang::solana_program::system_program;
-use std::str::FromStr;
+use anchor_lang::Discriminator;
+use anchor_spl::associated_token::{get_associated_token_address, AssociatedToken};
+use anchor_spl::metadata::mpl_token_metadata::types::DataV2;
+use anchor_spl::metadata::{create_metadata_accounts_v3, CreateMetadataAccountsV3, Metadata};
+use anchor_spl::token::{
+    self, FreezeAccount, Mint, MintTo, SetAuthority, ThawAccount, Token, TokenAccount, Transfer,
+};
 
-declare_id!("BiometricNftProgram1111111111111111111111");
+declare_id!("3BRGASWgfiPsxwVQq2W6JKLuWvZRBRSd3gkgfeDt9yoA");
 
 #[program]
 pub mod biometric_nft {
@@ -17,159 +22,2212 @@ pub mod biometric_nft {
         emotion_data: EmotionData,
         quality_score: f64,
         biometric_hash: String,
+        biometric_salt: [u8; 32],
+        transfer_policy: TransferPolicy,
+        consent_flags: u8,
     ) -> Result<()> {
         require!(quality_score >= 0.7, ErrorCode::LowQualityScore);
-        require!(biometric_hash.len() == 64, ErrorCode::InvalidBiometricHash);
+        require!(consent_flags & CONSENT_BIOMETRIC_STORAGE != 0, ErrorCode::ConsentRequired);
+        require!(is_hex_digest(&biometric_hash), ErrorCode::InvalidBiometricHash);
+        require!(biometric_salt != [0u8; 32], ErrorCode::InvalidSalt);
 
         let nft_account = &mut ctx.accounts.nft_account;
         let clock = Clock::get()?;
 
         nft_account.owner = *ctx.accounts.payer.key;
-        nft_account.emotion_data = emotion_data;
+        // The initial emotion is only recorded when the owner consents to history
+        let history_consent = consent_flags & CONSENT_EMOTION_HISTORY != 0;
+        let compact = if history_consent {
+            CompactEmotion::from(&emotion_data)
+        } else {
+            CompactEmotion::erased()
+        };
+
+        nft_account.emotion_data = compact;
         nft_account.quality_score = quality_score;
         nft_account.biometric_hash = biometric_hash;
         nft_account.is_verified = false;
         nft_account.created_at = clock.unix_timestamp;
-        nft_account.emotion_history = vec![emotion_data.clone()];
+        nft_account.emotion_history = if history_consent { vec![compact] } else { Vec::new() };
+        nft_account.biometric_salt = biometric_salt;
+        nft_account.hash_version = HASH_VERSION_SALTED;
+        nft_account.transfer_policy = transfer_policy;
+        nft_account.unlock_approval = None;
+        nft_account.mint = Pubkey::default();
+        nft_account.consent_flags = consent_flags & CONSENT_ALL;
+        nft_account.zk_nonce = 0;
 
         msg!("Biometric NFT initialized: {:?}", nft_account.key());
         msg!("Owner: {:?}", nft_account.owner);
-        msg!("Primary emotion: {:?}", emotion_data.primary_emotion);
+        msg!("Primary emotion: {:?}", compact.primary_emotion);
         msg!("Quality score: {}", quality_score);
 
+        emit!(NFTInitialized {
+            nft: nft_account.key(),
+            owner: nft_account.owner,
+            primary_emotion: compact.primary_emotion,
+            quality_score,
+            transfer_policy,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Mint a frozen supply-1 SPL token with Metaplex metadata for the NFT
+    pub fn mint_nft_token(
+        ctx: Context<MintNFTToken>,
+        name: String,
+        symbol: String,
+        uri: String,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.nft_account.owner == *ctx.accounts.owner.key,
+            ErrorCode::Unauthorized
+        );
+        require!(
+            ctx.accounts.nft_account.mint == Pubkey::default(),
+            ErrorCode::TokenAlreadyMinted
+        );
+
+        let authority_bump = ctx.bumps.mint_authority;
+        let signer_seeds: &[&[&[u8]]] = &[&[MINT_AUTHORITY_SEED, &[authority_bump]]];
+        let mint_authority = ctx.accounts.mint_authority.to_account_info();
+        let token_program = ctx.accounts.token_program.to_account_info();
+
+        token::mint_to(
+            CpiContext::new_with_signer(
+                token_program.clone(),
+                MintTo {
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.token_account.to_account_info(),
+                    authority: mint_authority.clone(),
+                },
+                signer_seeds,
+            ),
+            1,
+        )?;
+
+        create_metadata_accounts_v3(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_metadata_program.to_account_info(),
+                CreateMetadataAccountsV3 {
+                    metadata: ctx.accounts.metadata.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    mint_authority: mint_authority.clone(),
+                    payer: ctx.accounts.owner.to_account_info(),
+                    update_authority: mint_authority.clone(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                    rent: ctx.accounts.rent.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            DataV2 {
+                name,
+                symbol,
+                uri,
+                seller_fee_basis_points: 0,
+                creators: None,
+                collection: None,
+                uses: None,
+            },
+            true,
+            true,
+            None,
+        )?;
+
+        // Cap supply at one by dropping the mint authority
+        token::set_authority(
+            CpiContext::new_with_signer(
+                token_program.clone(),
+                SetAuthority {
+                    current_authority: mint_authority.clone(),
+                    account_or_mint: ctx.accounts.mint.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            token::spl_token::instruction::AuthorityType::MintTokens,
+            None,
+        )?;
+
+        // Freezing the holder's token account keeps the token soulbound in wallets
+        token::freeze_account(CpiContext::new_with_signer(
+            token_program,
+            FreezeAccount {
+                account: ctx.accounts.token_account.to_account_info(),
+                mint: ctx.accounts.mint.to_account_info(),
+                authority: mint_authority,
+            },
+            signer_seeds,
+        ))?;
+
+        let nft_account = &mut ctx.accounts.nft_account;
+        nft_account.mint = ctx.accounts.mint.key();
+
+        msg!("Token minted for NFT: {:?}", nft_account.key());
+        msg!("Mint: {:?}", nft_account.mint);
+
+        emit!(NFTTokenMinted {
+            nft: nft_account.key(),
+            owner: nft_account.owner,
+            mint: nft_account.mint,
+            metadata: ctx.accounts.metadata.key(),
+        });
+
+        Ok(())
+    }
+
+    /// Create the verifier registry, making the program's upgrade authority its admin
+    pub fn initialize_verifier_registry(ctx: Context<InitializeVerifierRegistry>) -> Result<()> {
+        let registry = &mut ctx.accounts.verifier_registry;
+
+        registry.admin = *ctx.accounts.admin.key;
+        registry.verifiers = Vec::new();
+        registry.bump = ctx.bumps.verifier_registry;
+
+        msg!("Verifier registry initialized with admin: {:?}", registry.admin);
+
+        Ok(())
+    }
+
+    /// Register a verifier allowed to attest biometric verifications
+    pub fn add_verifier(ctx: Context<ManageVerifiers>, verifier: Pubkey) -> Result<()> {
+        let registry = &mut ctx.accounts.verifier_registry;
+
+        require!(registry.admin == *ctx.accounts.admin.key, ErrorCode::Unauthorized);
+        require!(!registry.verifiers.contains(&verifier), ErrorCode::VerifierAlreadyRegistered);
+        require!(registry.verifiers.len() < MAX_VERIFIERS, ErrorCode::VerifierRegistryFull);
+
+        registry.verifiers.push(verifier);
+        msg!("Verifier registered: {:?}", verifier);
+
+        Ok(())
+    }
+
+    /// Remove a verifier from the registry
+    pub fn remove_verifier(ctx: Context<ManageVerifiers>, verifier: Pubkey) -> Result<()> {
+        let registry = &mut ctx.accounts.verifier_registry;
+
+        require!(registry.admin == *ctx.accounts.admin.key, ErrorCode::Unauthorized);
+
+        let index = registry
+            .verifiers
+            .iter()
+            .position(|registered| *registered == verifier)
+            .ok_or(ErrorCode::UnregisteredVerifier)?;
+        registry.verifiers.swap_remove(index);
+        msg!("Verifier removed: {:?}", verifier);
+
+        Ok(())
+    }
+
+    /// Create the device registry, administered by the program config admin
+    pub fn initialize_device_registry(ctx: Context<InitializeDeviceRegistry>) -> Result<()> {
+        require!(
+            ctx.accounts.program_config.admin == *ctx.accounts.admin.key,
+            ErrorCode::Unauthorized
+        );
+
+        let registry = &mut ctx.accounts.device_registry;
+        registry.admin = *ctx.accounts.admin.key;
+        registry.vendors = Vec::new();
+        registry.devices = Vec::new();
+        registry.bump = ctx.bumps.device_registry;
+
+        msg!("Device registry initialized with admin: {:?}", registry.admin);
+
+        Ok(())
+    }
+
+    /// Approve a hardware vendor allowed to register devices
+    pub fn add_device_vendor(ctx: Context<ManageDevices>, vendor: Pubkey) -> Result<()> {
+        let registry = &mut ctx.accounts.device_registry;
+
+        require!(registry.admin == *ctx.accounts.authority.key, ErrorCode::Unauthorized);
+        require!(!registry.vendors.contains(&vendor), ErrorCode::VendorAlreadyRegistered);
+        require!(registry.vendors.len() < MAX_DEVICE_VENDORS, ErrorCode::DeviceRegistryFull);
+
+        registry.vendors.push(vendor);
+        msg!("Device vendor registered: {:?}", vendor);
+
+        Ok(())
+    }
+
+    /// Register a device signing key under the calling vendor
+    pub fn register_device(ctx: Context<ManageDevices>, device: Pubkey) -> Result<()> {
+        let vendor = *ctx.accounts.authority.key;
+        let registry = &mut ctx.accounts.device_registry;
+
+        require!(registry.vendors.contains(&vendor), ErrorCode::UnregisteredVendor);
+        require!(!registry.is_registered_device(&device), ErrorCode::DeviceAlreadyRegistered);
+        require!(registry.devices.len() < MAX_DEVICES, ErrorCode::DeviceRegistryFull);
+
+        registry.devices.push(RegisteredDevice { device, vendor });
+        msg!("Device {:?} registered by vendor {:?}", device, vendor);
+
+        Ok(())
+    }
+
+    /// Revoke a device, callable by its vendor or the registry admin
+    pub fn revoke_device(ctx: Context<ManageDevices>, device: Pubkey) -> Result<()> {
+        let authority = *ctx.accounts.authority.key;
+        let registry = &mut ctx.accounts.device_registry;
+
+        let index = registry
+            .devices
+            .iter()
+            .position(|registered| registered.device == device)
+            .ok_or(ErrorCode::UnregisteredDevice)?;
+        require!(
+            registry.devices[index].vendor == authority || registry.admin == authority,
+            ErrorCode::Unauthorized
+        );
+
+        registry.devices.swap_remove(index);
+        msg!("Device revoked: {:?}", device);
+
+        Ok(())
+    }
+
+    /// Create the program config account, administered by the program's upgrade authority
+    pub fn initialize_program_config(
+        ctx: Context<InitializeProgramConfig>,
+        quality_params: QualityParams,
+    ) -> Result<()> {
+        require!(quality_params.is_valid(), ErrorCode::InvalidQualityParams);
+
+        let config = &mut ctx.accounts.program_config;
+        config.admin = *ctx.accounts.admin.key;
+        config.quality_params = quality_params;
+        config.bump = ctx.bumps.program_config;
+
+        msg!("Program config initialized with admin: {:?}", config.admin);
+
+        Ok(())
+    }
+
+    /// Update the quality score formula parameters
+    pub fn set_quality_params(
+        ctx: Context<UpdateProgramConfig>,
+        quality_params: QualityParams,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.program_config;
+
+        require!(config.admin == *ctx.accounts.admin.key, ErrorCode::Unauthorized);
+        require!(quality_params.is_valid(), ErrorCode::InvalidQualityParams);
+
+        config.quality_params = quality_params;
+        msg!("Quality params updated: {:?}", quality_params);
+
+        Ok(())
+    }
+
+    /// Commit to a biometric verification without revealing any biometric data
+    pub fn commit_biometric(
+        ctx: Context<CommitBiometric>,
+        commitment: [u8; 32],
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.verifier_registry.is_registered(ctx.accounts.verifier.key),
+            ErrorCode::UnregisteredVerifier
+        );
+
+        let nft_account = &mut ctx.accounts.nft_account;
+        let clock = Clock::get()?;
+
+        require!(nft_account.has_consent(CONSENT_BIOMETRIC_STORAGE), ErrorCode::ConsentRevoked);
+        require!(nft_account.hash_version == HASH_VERSION_SALTED, ErrorCode::EnrollmentRequired);
+
+        // A new commitment replaces any stale one from the same or another verifier
+        nft_account.pending_commitment = commitment;
+        nft_account.commitment_verifier = *ctx.accounts.verifier.key;
+        nft_account.committed_at = clock.unix_timestamp;
+        nft_account.committed_slot = clock.slot;
+
+        msg!("Biometric commitment recorded for NFT: {:?}", nft_account.key());
+
         Ok(())
     }
 
-    /// Verify biometric data against stored hash
+    /// Reveal a committed verification and check it against the stored hash
+    ///
+    /// The verifier computes `salted_digest = SHA-256(salt || biometric_data)`
+    /// from the live capture off-chain and previously committed
+    /// `SHA-256(salted_digest || nonce)`. The program recomputes
+    /// `SHA-256(salt || salted_digest)` and compares it to the enrolled hash.
+    /// The revealed `salted_digest` is public on the ledger, but it is specific
+    /// to this NFT's salt and the raw template never leaves the verifier.
     pub fn verify_biometric(
         ctx: Context<VerifyBiometric>,
-        biometric_data: String,
+        salted_digest: [u8; 32],
+        nonce: [u8; 32],
     ) -> Result<()> {
+        // Re-checked on reveal in case the verifier was removed after committing
+        require!(
+            ctx.accounts.verifier_registry.is_registered(ctx.accounts.verifier.key),
+            ErrorCode::UnregisteredVerifier
+        );
+
         let nft_account = &mut ctx.accounts.nft_account;
+        let clock = Clock::get()?;
         
-        // Simple hash verification (in production, use proper cryptographic verification)
-        let computed_hash = Self::compute_biometric_hash(&biometric_data);
-        require!(computed_hash == nft_account.biometric_hash, ErrorCode::BiometricVerificationFailed);
+        require!(nft_account.has_consent(CONSENT_BIOMETRIC_STORAGE), ErrorCode::ConsentRevoked);
+        require!(nft_account.hash_version == HASH_VERSION_SALTED, ErrorCode::EnrollmentRequired);
+        require!(nft_account.pending_commitment != [0u8; 32], ErrorCode::NoPendingCommitment);
+        require!(
+            nft_account.commitment_verifier == *ctx.accounts.verifier.key,
+            ErrorCode::VerifierMismatch
+        );
+        require!(clock.slot > nft_account.committed_slot, ErrorCode::RevealTooEarly);
+        require!(
+            clock.unix_timestamp - nft_account.committed_at <= REVEAL_WINDOW_SECONDS,
+            ErrorCode::CommitmentExpired
+        );
+
+        let expected_commitment = compute_commitment(&salted_digest, &nonce);
+        require!(
+            constant_time_eq(&expected_commitment, &nft_account.pending_commitment),
+            ErrorCode::CommitmentMismatch
+        );
+        require!(
+            constant_time_eq(
+                compute_biometric_hash(&nft_account.biometric_salt, &salted_digest).as_bytes(),
+                nft_account.biometric_hash.as_bytes()
+            ),
+            ErrorCode::BiometricVerificationFailed
+        );
 
+        nft_account.pending_commitment = [0u8; 32];
         nft_account.is_verified = true;
+        nft_account.verified_by = *ctx.accounts.verifier.key;
+        nft_account.verified_at = clock.unix_timestamp;
         msg!("Biometric verification successful for NFT: {:?}", nft_account.key());
+        msg!("Attested by verifier: {:?}", nft_account.verified_by);
+
+        emit!(BiometricVerified {
+            nft: nft_account.key(),
+            owner: nft_account.owner,
+            verifier: nft_account.verified_by,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Store the Groth16 verifying key used by verify_biometric_zk
+    pub fn initialize_zk_verifying_key(
+        ctx: Context<InitializeZkVerifyingKey>,
+        verifying_key: Groth16VerifyingKey,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.program_config.admin == *ctx.accounts.admin.key,
+            ErrorCode::Unauthorized
+        );
+
+        let zk_key = &mut ctx.accounts.zk_verifying_key;
+        zk_key.verifying_key = verifying_key;
+        zk_key.bump = ctx.bumps.zk_verifying_key;
+
+        msg!("ZK verifying key initialized: {:?}", zk_key.key());
+
+        Ok(())
+    }
+
+    /// Replace the Groth16 verifying key after a circuit upgrade
+    pub fn set_zk_verifying_key(
+        ctx: Context<UpdateZkVerifyingKey>,
+        verifying_key: Groth16VerifyingKey,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.program_config.admin == *ctx.accounts.admin.key,
+            ErrorCode::Unauthorized
+        );
+
+        ctx.accounts.zk_verifying_key.verifying_key = verifying_key;
+        msg!("ZK verifying key updated: {:?}", ctx.accounts.zk_verifying_key.key());
+
+        Ok(())
+    }
+
+    /// Verify a Groth16 proof that the owner's live biometric matches the enrolled hash
+    ///
+    /// The circuit's public inputs are the stored hash and the owner key, each
+    /// split into two 128-bit big-endian halves, followed by the NFT's
+    /// `zk_nonce`, so no biometric data or digest preimage is revealed. Binding
+    /// the owner and a nonce bumped on every success stops a proof from being
+    /// replayed later or by a new owner.
+    pub fn verify_biometric_zk(
+        ctx: Context<VerifyBiometricZk>,
+        proof: Groth16Proof,
+    ) -> Result<()> {
+        let zk_key = &ctx.accounts.zk_verifying_key;
+        let nft_account = &mut ctx.accounts.nft_account;
+        let clock = Clock::get()?;
+
+        require!(nft_account.owner == *ctx.accounts.owner.key, ErrorCode::Unauthorized);
+        require!(nft_account.has_consent(CONSENT_BIOMETRIC_STORAGE), ErrorCode::ConsentRevoked);
+        require!(nft_account.hash_version == HASH_VERSION_SALTED, ErrorCode::EnrollmentRequired);
+
+        let public_inputs =
+            zk_public_inputs(&nft_account.biometric_hash, &nft_account.owner, nft_account.zk_nonce)
+                .ok_or(ErrorCode::InvalidBiometricHash)?;
+        require!(
+            verify_groth16(&zk_key.verifying_key, &proof, &public_inputs)?,
+            ErrorCode::BiometricVerificationFailed
+        );
+
+        nft_account.zk_nonce += 1;
+        nft_account.is_verified = true;
+        nft_account.verified_by = zk_key.key();
+        nft_account.verified_at = clock.unix_timestamp;
+        msg!("ZK biometric verification successful for NFT: {:?}", nft_account.key());
+
+        emit!(BiometricVerified {
+            nft: nft_account.key(),
+            owner: nft_account.owner,
+            verifier: nft_account.verified_by,
+            timestamp: clock.unix_timestamp,
+        });
 
         Ok(())
     }
 
     /// Update emotion data and add to history
+    ///
+    /// Passing the device registry and instructions sysvar requires the previous
+    /// instruction to be an ed25519 signature by a registered device over
+    /// `device_attestation_message`, and flags the record as device attested.
     pub fn update_emotion(
         ctx: Context<UpdateEmotion>,
         new_emotion_data: EmotionData,
     ) -> Result<()> {
+        let nft_key = ctx.accounts.nft_account.key();
+        let mut compact = CompactEmotion::from(&new_emotion_data);
+
+        let attesting_device = match (&ctx.accounts.device_registry, &ctx.accounts.instructions_sysvar) {
+            (Some(registry), Some(instructions)) => {
+                let message = device_attestation_message(&nft_key, &compact);
+                let device = verify_device_signature(&instructions.to_account_info(), &message)?;
+                require!(registry.is_registered_device(&device), ErrorCode::UnregisteredDevice);
+                Some(device)
+            }
+            (None, None) => None,
+            _ => return err!(ErrorCode::IncompleteDeviceAttestation),
+        };
+        compact.device_attested = attesting_device.is_some();
+
         let nft_account = &mut ctx.accounts.nft_account;
         
         require!(nft_account.owner == *ctx.accounts.owner.key, ErrorCode::Unauthorized);
+        require!(nft_account.has_consent(CONSENT_EMOTION_HISTORY), ErrorCode::ConsentRevoked);
         require!(new_emotion_data.confidence >= 0.5, ErrorCode::LowConfidence);
+        // Also stops a device signature from being resubmitted to pad the history
+        require!(
+            compact.timestamp > nft_account.emotion_data.timestamp,
+            ErrorCode::NonMonotonicTimestamps
+        );
 
-        nft_account.emotion_data = new_emotion_data.clone();
-        nft_account.emotion_history.push(new_emotion_data);
-
-        // Keep only last 100 emotion records to prevent account bloat
-        if nft_account.emotion_history.len() > 100 {
-            nft_account.emotion_history.remove(0);
+        nft_account.record_emotions(&[compact]);
+        if let Some(device) = attesting_device {
+            msg!("Emotion attested by device: {:?}", device);
         }
 
         msg!("Emotion updated for NFT: {:?}", nft_account.key());
         msg!("New primary emotion: {:?}", new_emotion_data.primary_emotion);
 
+        emit!(EmotionUpdated {
+            nft: nft_account.key(),
+            owner: nft_account.owner,
+            emotion: compact,
+            history_len: nft_account.emotion_history.len() as u16,
+        });
+
+        Ok(())
+    }
+
+    /// Update emotion data with small signed deltas against the latest record
+    pub fn update_emotion_delta(
+        ctx: Context<UpdateEmotionRecords>,
+        delta: EmotionDelta,
+    ) -> Result<()> {
+        let nft_account = &mut ctx.accounts.nft_account;
+        
+        require!(nft_account.owner == *ctx.accounts.owner.key, ErrorCode::Unauthorized);
+        require!(nft_account.has_consent(CONSENT_EMOTION_HISTORY), ErrorCode::ConsentRevoked);
+        require!(delta.elapsed_seconds > 0, ErrorCode::NonMonotonicTimestamps);
+
+        let compact = nft_account.emotion_data.apply_delta(&delta);
+        require!(compact.confidence_f64() >= 0.5, ErrorCode::LowConfidence);
+
+        nft_account.record_emotions(&[compact]);
+
+        msg!("Emotion delta applied for NFT: {:?}", nft_account.key());
+
+        emit!(EmotionUpdated {
+            nft: nft_account.key(),
+            owner: nft_account.owner,
+            emotion: compact,
+            history_len: nft_account.emotion_history.len() as u16,
+        });
+
+        Ok(())
+    }
+
+    /// Apply a batch of offline-collected emotion samples in timestamp order
+    pub fn update_emotions_batch(
+        ctx: Context<UpdateEmotionRecords>,
+        emotions: Vec<CompactEmotion>,
+    ) -> Result<()> {
+        let nft_account = &mut ctx.accounts.nft_account;
+        
+        require!(nft_account.owner == *ctx.accounts.owner.key, ErrorCode::Unauthorized);
+        require!(nft_account.has_consent(CONSENT_EMOTION_HISTORY), ErrorCode::ConsentRevoked);
+        require!(!emotions.is_empty(), ErrorCode::EmptyEmotionBatch);
+        require!(emotions.len() <= MAX_EMOTION_BATCH, ErrorCode::EmotionBatchTooLarge);
+
+        // Samples must continue strictly after the latest stored record
+        let mut previous_timestamp = nft_account.emotion_data.timestamp;
+        for emotion in &emotions {
+            require!(emotion.timestamp > previous_timestamp, ErrorCode::NonMonotonicTimestamps);
+            require!(emotion.is_in_range(), ErrorCode::VadOutOfRange);
+            require!(emotion.confidence_f64() >= 0.5, ErrorCode::LowConfidence);
+            previous_timestamp = emotion.timestamp;
+        }
+
+        // Attestation only comes from update_emotion's signature check
+        let emotions: Vec<CompactEmotion> = emotions
+            .into_iter()
+            .map(|emotion| CompactEmotion { device_attested: false, ..emotion })
+            .collect();
+        nft_account.record_emotions(&emotions);
+
+        msg!("Emotion batch applied for NFT: {:?}", nft_account.key());
+        msg!("Records: {}", emotions.len());
+
+        emit!(EmotionBatchUpdated {
+            nft: nft_account.key(),
+            owner: nft_account.owner,
+            count: emotions.len() as u8,
+            first_timestamp: emotions[0].timestamp,
+            latest: nft_account.emotion_data,
+            history_len: nft_account.emotion_history.len() as u16,
+        });
+
+        Ok(())
+    }
+
+    /// Recompute quality_score from the NFT's emotion history
+    pub fn recompute_quality(ctx: Context<RecomputeQuality>) -> Result<()> {
+        require!(
+            ctx.accounts.verifier_registry.is_registered(ctx.accounts.verifier.key),
+            ErrorCode::UnregisteredVerifier
+        );
+
+        let params = ctx.accounts.program_config.quality_params;
+        let nft_account = &mut ctx.accounts.nft_account;
+        let clock = Clock::get()?;
+
+        require!(nft_account.has_consent(CONSENT_EMOTION_HISTORY), ErrorCode::ConsentRevoked);
+        require!(!nft_account.emotion_history.is_empty(), ErrorCode::EmptyEmotionHistory);
+
+        let previous_score = nft_account.quality_score;
+        nft_account.quality_score = compute_quality_score(&nft_account.emotion_history, &params);
+
+        msg!("Quality score recomputed for NFT: {:?}", nft_account.key());
+        msg!("Quality score: {} -> {}", previous_score, nft_account.quality_score);
+
+        emit!(QualityRecomputed {
+            nft: nft_account.key(),
+            verifier: *ctx.accounts.verifier.key,
+            previous_score,
+            quality_score: nft_account.quality_score,
+            record_count: nft_account.emotion_history.len() as u16,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Compute or refresh the compatibility score between two verified NFTs
+    pub fn compute_compatibility(ctx: Context<ComputeCompatibility>) -> Result<()> {
+        let nft_a = &ctx.accounts.nft_a;
+        let nft_b = &ctx.accounts.nft_b;
+        let requester = *ctx.accounts.requester.key;
+        let clock = Clock::get()?;
+
+        require!(nft_a.key() < nft_b.key(), ErrorCode::UnorderedCompatibilityPair);
+        require!(
+            nft_a.owner == requester || nft_b.owner == requester,
+            ErrorCode::Unauthorized
+        );
+        require!(nft_a.is_verified && nft_b.is_verified, ErrorCode::NotVerified);
+        require!(
+            nft_a.has_consent(CONSENT_EMOTION_HISTORY) && nft_b.has_consent(CONSENT_EMOTION_HISTORY),
+            ErrorCode::ConsentRevoked
+        );
+
+        let (score_bps, samples) =
+            compatibility_score_bps(&nft_a.emotion_history, &nft_b.emotion_history)
+                .ok_or(ErrorCode::EmptyEmotionHistory)?;
+
+        let compatibility = &mut ctx.accounts.compatibility;
+        require!(
+            compatibility.computed_at == 0
+                || clock.unix_timestamp - compatibility.computed_at >= COMPATIBILITY_COOLDOWN_SECONDS,
+            ErrorCode::CompatibilityCooldown
+        );
+
+        compatibility.nft_a = nft_a.key();
+        compatibility.nft_b = nft_b.key();
+        compatibility.score_bps = score_bps;
+        compatibility.samples = samples;
+        compatibility.computed_at = clock.unix_timestamp;
+        compatibility.bump = ctx.bumps.compatibility;
+
+        msg!("Compatibility computed: {:?} <-> {:?}", compatibility.nft_a, compatibility.nft_b);
+        msg!("Score: {} bps over {} samples", score_bps, samples);
+
+        emit!(CompatibilityComputed {
+            compatibility: compatibility.key(),
+            nft_a: compatibility.nft_a,
+            nft_b: compatibility.nft_b,
+            score_bps,
+            samples,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Approve a one-time unlock of the soulbound NFT for a specific new owner
+    pub fn request_unlock(
+        ctx: Context<RequestUnlock>,
+        new_owner: Pubkey,
+    ) -> Result<()> {
+        let registry = &ctx.accounts.verifier_registry;
+        let attester = *ctx.accounts.attester.key;
+        let nft_account = &mut ctx.accounts.nft_account;
+        let clock = Clock::get()?;
+
+        require!(nft_account.owner == *ctx.accounts.owner.key, ErrorCode::Unauthorized);
+        require!(new_owner != nft_account.owner, ErrorCode::InvalidTransfer);
+
+        match nft_account.transfer_policy {
+            TransferPolicy::Locked => return err!(ErrorCode::TransferLocked),
+            TransferPolicy::UnlockableByDAO => {
+                require!(registry.admin == attester, ErrorCode::InvalidUnlockAttester);
+            }
+            TransferPolicy::CompromisedRecovery => {
+                require!(registry.is_registered(&attester), ErrorCode::InvalidUnlockAttester);
+            }
+        }
+
+        nft_account.unlock_approval = Some(UnlockApproval {
+            new_owner,
+            approved_by: attester,
+            approved_at: clock.unix_timestamp,
+        });
+
+        msg!("Unlock approved for NFT: {:?}", nft_account.key());
+        msg!("Approved by {:?} for new owner {:?}", attester, new_owner);
+
         Ok(())
     }
 
-    /// Transfer NFT (soulbound - only allowed under specific conditions)
+    /// Transfer NFT (soulbound - only allowed with an approved unlock)
     pub fn transfer_nft(
         ctx: Context<TransferNFT>,
         new_owner: Pubkey,
     ) -> Result<()> {
+        let current_owner = ctx.accounts.current_owner.key();
+        let clock = Clock::get()?;
+
+        // Failed attempts are still emitted so dashboards see why a transfer was refused
+        let outcome = authorize_transfer(
+            &ctx.accounts.nft_account,
+            &current_owner,
+            &new_owner,
+            clock.unix_timestamp,
+        )
+        .and_then(|()| check_token_accounts(ctx.accounts, &new_owner));
+        if let Err(error) = outcome {
+            emit!(TransferAttempted {
+                nft: ctx.accounts.nft_account.key(),
+                from: current_owner,
+                to: new_owner,
+                success: false,
+                failure_code: Some(u32::from(error)),
+                failure_reason: Some(error.to_string()),
+                timestamp: clock.unix_timestamp,
+            });
+            return Err(error.into());
+        }
+
+        // Minted NFTs move their frozen token along with the account
+        if ctx.accounts.nft_account.mint != Pubkey::default() {
+            let mint_authority_bump = ctx.bumps.mint_authority.ok_or(ErrorCode::MissingTokenAccounts)?;
+            move_soulbound_token(ctx.accounts, mint_authority_bump)?;
+        }
+
         let nft_account = &mut ctx.accounts.nft_account;
-        
-        require!(nft_account.owner == *ctx.accounts.current_owner.key, ErrorCode::Unauthorized);
-        require!(nft_account.is_verified, ErrorCode::NotVerified);
-        require!(new_owner != nft_account.owner, ErrorCode::InvalidTransfer);
 
-        // Soulbound logic: only allow transfer if biometric verification is compromised
-        // In a real implementation, this would require additional verification
+        // Enrollment, consent and emotion data belong to the previous owner, so the
+        // new owner starts empty, grants consent by re-enrolling through
+        // enroll_biometric_hash and then verifies again
+        nft_account.unlock_approval = None;
         nft_account.owner = new_owner;
+        nft_account.is_verified = false;
+        nft_account.biometric_hash = String::new();
+        nft_account.biometric_salt = [0u8; 32];
+        nft_account.hash_version = HASH_VERSION_UNENROLLED;
+        nft_account.pending_commitment = [0u8; 32];
+        nft_account.consent_flags = 0;
+        nft_account.emotion_data = CompactEmotion::erased();
+        nft_account.emotion_history = Vec::new();
+        nft_account.quality_score = 0.0;
+
+        msg!("NFT transferred from {:?} to {:?}", current_owner, new_owner);
+
+        // Only emitted once the token, if any, has moved
+        emit!(TransferAttempted {
+            nft: nft_account.key(),
+            from: current_owner,
+            to: new_owner,
+            success: true,
+            failure_code: None,
+            failure_reason: None,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Enroll a salted biometric hash for an NFT without one, such as after a transfer
+    pub fn enroll_biometric_hash(
+        ctx: Context<EnrollBiometricHash>,
+        salted_hash: String,
+        biometric_salt: [u8; 32],
+        consent_flags: u8,
+    ) -> Result<()> {
+        let nft_account = &mut ctx.accounts.nft_account;
+
+        require!(nft_account.owner == *ctx.accounts.owner.key, ErrorCode::Unauthorized);
+        require!(nft_account.hash_version == HASH_VERSION_UNENROLLED, ErrorCode::AlreadyEnrolled);
+        require!(consent_flags & CONSENT_BIOMETRIC_STORAGE != 0, ErrorCode::ConsentRequired);
+        require!(is_hex_digest(&salted_hash), ErrorCode::InvalidBiometricHash);
+        require!(biometric_salt != [0u8; 32], ErrorCode::InvalidSalt);
+
+        // The raw template never touches the chain: the owner re-enrolls with a
+        // client-side salted digest, and the NFT must be verified again against it
+        nft_account.biometric_hash = salted_hash;
+        nft_account.biometric_salt = biometric_salt;
+        nft_account.hash_version = HASH_VERSION_SALTED;
+        nft_account.is_verified = false;
+        nft_account.consent_flags = consent_flags & CONSENT_ALL;
 
-        msg!("NFT transferred from {:?} to {:?}", ctx.accounts.current_owner.key(), new_owner);
+        msg!("Salted biometric hash enrolled for NFT: {:?}", nft_account.key());
 
         Ok(())
     }
 
-    /// Helper function to compute biometric hash
-    fn compute_biometric_hash(data: &str) -> String {
-        // Simple hash implementation - replace with proper cryptographic hash in production
-        use anchor_lang::solana_program::hash::hash;
-        let hash_result = hash(data.as_bytes());
-        format!("{:x}", hash_result)
+    /// Rewrite an NFTAccount created before the compact layout into the current one
+    ///
+    /// The old unsalted hash is dropped rather than carried over, so the owner
+    /// enrolls a salted hash through enroll_biometric_hash afterwards. Legacy
+    /// accounts predate consent flags, so the emotion history is only kept when
+    /// the owner opts in here.
+    pub fn migrate_legacy_account(
+        ctx: Context<MigrateLegacyAccount>,
+        keep_emotion_history: bool,
+    ) -> Result<()> {
+        let account_info = ctx.accounts.nft_account.to_account_info();
+        let owner = *ctx.accounts.owner.key;
+
+        let legacy = {
+            let data = account_info.try_borrow_data()?;
+            require!(
+                data.len() >= 8 && data[..8] == NFTAccount::DISCRIMINATOR,
+                ErrorCode::InvalidLegacyAccount
+            );
+            require!(NFTAccount::try_deserialize(&mut &data[..]).is_err(), ErrorCode::NotLegacyAccount);
+            LegacyNFTAccount::deserialize(&mut &data[8..]).map_err(|_| error!(ErrorCode::InvalidLegacyAccount))?
+        };
+        require!(legacy.owner == owner, ErrorCode::Unauthorized);
+
+        let migrated = migrate_legacy_layout(legacy, keep_emotion_history);
+
+        // The owner tops the account up to rent exemption for the new size
+        let required_lamports = Rent::get()?.minimum_balance(NFTAccount::SPACE);
+        let current_lamports = account_info.lamports();
+        if current_lamports < required_lamports {
+            anchor_lang::system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    anchor_lang::system_program::Transfer {
+                        from: ctx.accounts.owner.to_account_info(),
+                        to: account_info.clone(),
+                    },
+                ),
+                required_lamports - current_lamports,
+            )?;
+        }
+
+        account_info.realloc(NFTAccount::SPACE, true)?;
+        migrated.try_serialize(&mut &mut account_info.try_borrow_mut_data()?[..])?;
+
+        msg!("Legacy NFT account migrated: {:?}", account_info.key());
+        msg!("Emotion records kept: {}", migrated.emotion_history.len());
+
+        Ok(())
     }
-}
 
-/// Accounts for initializing a new NFT
-#[derive(Accounts)]
-pub struct InitializeNFT<'info> {
-    #[account(
-        init,
-        payer = payer,
-        space = 8 + std::mem::size_of::<NFTAccount>() + 1024 // Extra space for emotion history
-    )]
-    pub nft_account: Account<'info, NFTAccount>,
-    
-    #[account(mut)]
-    pub payer: Signer<'info>,
-    
-    pub system_program: Program<'info, System>,
-}
+    /// Revoke consent and erase the stored biometric hash and emotion history
+    pub fn revoke_biometric_consent(ctx: Context<RevokeBiometricConsent>) -> Result<()> {
+        let nft_account = &mut ctx.accounts.nft_account;
+        let clock = Clock::get()?;
 
-/// Accounts for verifying biometric data
-#[derive(Accounts)]
-pub struct VerifyBiometric<'info> {
-    #[account(mut)]
-    pub nft_account: Account<'info, NFTAccount>,
-    
-    pub verifier: Signer<'info>,
+        require!(nft_account.owner == *ctx.accounts.owner.key, ErrorCode::Unauthorized);
+        require!(nft_account.consent_flags != 0, ErrorCode::ConsentRevoked);
+
+        // Consent can only be granted again by re-enrolling through enroll_biometric_hash
+        nft_account.consent_flags = 0;
+        nft_account.biometric_hash = String::new();
+        nft_account.biometric_salt = [0u8; 32];
+        nft_account.hash_version = HASH_VERSION_UNENROLLED;
+        nft_account.pending_commitment = [0u8; 32];
+        nft_account.is_verified = false;
+        nft_account.emotion_data = CompactEmotion::erased();
+        nft_account.emotion_history = Vec::new();
+
+        msg!("Biometric consent revoked and data erased for NFT: {:?}", nft_account.key());
+
+        emit!(ConsentRevoked {
+            nft: nft_account.key(),
+            owner: nft_account.owner,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
 }
 
-/// Accounts for updating emotion data
-#[derive(Accounts)]
-pub struct UpdateEmotion<'info> {
-    #[account(mut)]
-    pub nft_account: Account<'info, NFTAccount>,
-    
-    pub owner: Signer<'info>,
+/// No usable hash is enrolled (cleared on transfer, consent revocation or legacy
+/// migration) - enroll through enroll_biometric_hash
+pub const HASH_VERSION_UNENROLLED: u8 = 0;
+/// SHA-256(salt || SHA-256(salt || biometric_data)), hex encoded
+pub const HASH_VERSION_SALTED: u8 = 1;
+
+/// Compute the per-NFT digest a verifier reveals, SHA-256(salt || biometric_data)
+pub fn compute_salted_digest(salt: &[u8; 32], biometric_data: &[u8]) -> [u8; 32] {
+    use anchor_lang::solana_program::hash::hashv;
+    hashv(&[salt, biometric_data]).to_bytes()
 }
 
-/// Accounts for transferring NFT
-#[derive(Accounts)]
-pub struct TransferNFT<'info> {
-    #[account(mut)]
-    pub nft_account: Account<'info, NFTAccount>,
-    
-    #[account(mut)]
-    pub current_owner: Signer<'info>,
+/// Compute the enrolled biometric hash of a salted digest as lowercase hex
+pub fn compute_biometric_hash(salt: &[u8; 32], salted_digest: &[u8; 32]) -> String {
+    use anchor_lang::solana_program::hash::hashv;
+    to_hex(&hashv(&[salt, salted_digest]).to_bytes())
+}
+
+/// PDA seed for an NFT's SPL mint, followed by the NFTAccount key
+pub const MINT_SEED: &[u8] = b"mint";
+/// PDA seed for the mint, freeze and metadata update authority
+pub const MINT_AUTHORITY_SEED: &[u8] = b"mint_authority";
+
+/// PDA seed for the singleton program config
+pub const PROGRAM_CONFIG_SEED: &[u8] = b"program_config";
+/// Basis points making up a whole formula weight
+pub const BPS_DENOMINATOR: u16 = 10_000;
+
+/// PDA seed for the singleton Groth16 verifying key
+pub const ZK_VERIFYING_KEY_SEED: &[u8] = b"zk_verifying_key";
+/// Public inputs of the biometric match circuit (hash halves, owner halves, nonce)
+pub const ZK_PUBLIC_INPUTS: usize = 5;
+
+/// PDA seed for a compatibility score, followed by the ordered NFT pair
+pub const COMPATIBILITY_SEED: &[u8] = b"compatibility";
+/// Recent records per NFT compared by compute_compatibility
+pub const COMPATIBILITY_WINDOW: usize = 16;
+/// Minimum time between compatibility refreshes
+pub const COMPATIBILITY_COOLDOWN_SECONDS: i64 = 3_600;
+
+/// PDA seed for the singleton device registry
+pub const DEVICE_REGISTRY_SEED: &[u8] = b"device_registry";
+/// Maximum number of approved hardware vendors
+pub const MAX_DEVICE_VENDORS: usize = 16;
+/// Maximum number of registered devices
+pub const MAX_DEVICES: usize = 64;
+/// Domain separator prefixed to device attestation messages
+pub const DEVICE_ATTESTATION_DOMAIN: &[u8] = b"biometric-nft:emotion:v1";
+
+/// PDA seed for the singleton verifier registry
+pub const VERIFIER_REGISTRY_SEED: &[u8] = b"verifier_registry";
+/// Maximum number of registered verifiers
+pub const MAX_VERIFIERS: usize = 32;
+
+/// Maximum time between commit_biometric and verify_biometric
+pub const REVEAL_WINDOW_SECONDS: i64 = 300;
+
+/// Number of emotion records kept in NFTAccount::emotion_history
+pub const MAX_EMOTION_HISTORY: usize = 100;
+
+/// Owner consents to storing the salted biometric hash
+pub const CONSENT_BIOMETRIC_STORAGE: u8 = 1 << 0;
+/// Owner consents to recording emotion history
+pub const CONSENT_EMOTION_HISTORY: u8 = 1 << 1;
+/// Every consent bit currently defined
+pub const CONSENT_ALL: u8 = CONSENT_BIOMETRIC_STORAGE | CONSENT_EMOTION_HISTORY;
+
+/// Maximum records accepted by update_emotions_batch
+pub const MAX_EMOTION_BATCH: usize = 32;
+
+/// How long an approved unlock stays usable
+pub const UNLOCK_VALIDITY_SECONDS: i64 = 86_400;
+
+/// Compute the commit-reveal commitment SHA-256(salted_digest || nonce)
+pub fn compute_commitment(salted_digest: &[u8; 32], nonce: &[u8; 32]) -> [u8; 32] {
+    use anchor_lang::solana_program::hash::hashv;
+    hashv(&[salted_digest, nonce]).to_bytes()
+}
+
+/// Compare two byte slices without short-circuiting on the first mismatch
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b.iter()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// BN254 base field modulus, big-endian, used to negate G1 points
+const BN254_FIELD_MODULUS: [u8; 32] = [
+    0x30, 0x64, 0x4e, 0x72, 0xe1, 0x31, 0xa0, 0x29, 0xb8, 0x50, 0x45, 0xb6, 0x81, 0x81, 0x58, 0x5d,
+    0x97, 0x81, 0x6a, 0x91, 0x68, 0x71, 0xca, 0x8d, 0x3c, 0x20, 0x8c, 0x16, 0xd8, 0x7c, 0xfd, 0x47,
+];
+
+/// Build the circuit's public inputs from the stored hex hash, owner and nonce
+fn zk_public_inputs(
+    biometric_hash: &str,
+    owner: &Pubkey,
+    zk_nonce: u64,
+) -> Option<[[u8; 32]; ZK_PUBLIC_INPUTS]> {
+    if !is_hex_digest(biometric_hash) {
+        return None;
+    }
+
+    let nibble = |c: u8| if c.is_ascii_digit() { c - b'0' } else { c - b'a' + 10 };
+    let digest: Vec<u8> = biometric_hash
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| (nibble(pair[0]) << 4) | nibble(pair[1]))
+        .collect();
+
+    // Each 128-bit half is left-padded so it stays below the scalar field modulus
+    let mut inputs = [[0u8; 32]; ZK_PUBLIC_INPUTS];
+    inputs[0][16..].copy_from_slice(&digest[..16]);
+    inputs[1][16..].copy_from_slice(&digest[16..]);
+    inputs[2][16..].copy_from_slice(&owner.as_ref()[..16]);
+    inputs[3][16..].copy_from_slice(&owner.as_ref()[16..]);
+    inputs[4][24..].copy_from_slice(&zk_nonce.to_be_bytes());
+    Some(inputs)
+}
+
+/// Negate a big-endian G1 point (x, y) -> (x, p - y)
+fn negate_g1(point: &[u8; 64]) -> [u8; 64] {
+    let mut negated = *point;
+    if point[32..].iter().all(|byte| *byte == 0) {
+        return negated;
+    }
+
+    let mut borrow = 0i16;
+    for i in (0..32).rev() {
+        let mut diff = BN254_FIELD_MODULUS[i] as i16 - point[32 + i] as i16 - borrow;
+        borrow = if diff < 0 { 1 } else { 0 };
+        if diff < 0 {
+            diff += 256;
+        }
+        negated[32 + i] = diff as u8;
+    }
+    negated
+}
+
+/// Check e(-A, B) * e(alpha, beta) * e(vk_x, gamma) * e(C, delta) == 1
+fn verify_groth16(
+    verifying_key: &Groth16VerifyingKey,
+    proof: &Groth16Proof,
+    public_inputs: &[[u8; 32]; ZK_PUBLIC_INPUTS],
+) -> Result<bool> {
+    use anchor_lang::solana_program::alt_bn128::prelude::{
+        alt_bn128_addition, alt_bn128_multiplication, alt_bn128_pairing,
+    };
+
+    // vk_x = IC[0] + sum(input_i * IC[i + 1])
+    let mut vk_x = verifying_key.ic[0].to_vec();
+    for (input, ic) in public_inputs.iter().zip(verifying_key.ic[1..].iter()) {
+        let product = alt_bn128_multiplication(&[&ic[..], &input[..]].concat())
+            .map_err(|_| error!(ErrorCode::InvalidZkProof))?;
+        vk_x = alt_bn128_addition(&[&vk_x[..], &product[..]].concat())
+            .map_err(|_| error!(ErrorCode::InvalidZkProof))?;
+    }
+
+    let pairing_input = [
+        &negate_g1(&proof.a)[..],
+        &proof.b[..],
+        &verifying_key.alpha_g1[..],
+        &verifying_key.beta_g2[..],
+        &vk_x[..],
+        &verifying_key.gamma_g2[..],
+        &proof.c[..],
+        &verifying_key.delta_g2[..],
+    ]
+    .concat();
+    let result = alt_bn128_pairing(&pairing_input).map_err(|_| error!(ErrorCode::InvalidZkProof))?;
+
+    Ok(is_pairing_identity(&result))
+}
+
+/// Check that an alt_bn128_pairing result is the big-endian encoding of 1
+fn is_pairing_identity(result: &[u8]) -> bool {
+    result.len() == 32 && result[..31].iter().all(|byte| *byte == 0) && result[31] == 1
+}
+
+/// Bytes a device signs to attest an emotion record for a specific NFT
+pub fn device_attestation_message(nft: &Pubkey, emotion: &CompactEmotion) -> Vec<u8> {
+    let unattested = CompactEmotion { device_attested: false, ..*emotion };
+    let mut message = Vec::with_capacity(DEVICE_ATTESTATION_DOMAIN.len() + 32 + CompactEmotion::SIZE);
+    message.extend_from_slice(DEVICE_ATTESTATION_DOMAIN);
+    message.extend_from_slice(nft.as_ref());
+    // Serializing a fixed-size struct into a Vec cannot fail
+    unattested.serialize(&mut message).unwrap();
+    message
+}
+
+/// Check that the previous instruction is a single ed25519 signature over
+/// `expected_message` and return the signing key
+fn verify_device_signature(instructions: &AccountInfo, expected_message: &[u8]) -> Result<Pubkey> {
+    use anchor_lang::solana_program::ed25519_program;
+    use anchor_lang::solana_program::sysvar::instructions::{
+        load_current_index_checked, load_instruction_at_checked,
+    };
+
+    let current_index = load_current_index_checked(instructions)?;
+    require!(current_index > 0, ErrorCode::InvalidDeviceSignature);
+    let ed25519_ix = load_instruction_at_checked((current_index - 1) as usize, instructions)?;
+    require!(ed25519_ix.program_id == ed25519_program::ID, ErrorCode::InvalidDeviceSignature);
+
+    parse_ed25519_signer(&ed25519_ix.data, expected_message)
+}
+
+/// Parse ed25519 program instruction data holding one self-contained signature
+/// over `expected_message` and return the signing key
+fn parse_ed25519_signer(data: &[u8], expected_message: &[u8]) -> Result<Pubkey> {
+    const HEADER_LEN: usize = 2;
+    const OFFSETS_LEN: usize = 14;
+    const CURRENT_INSTRUCTION: u16 = u16::MAX;
+
+    require!(data.len() >= HEADER_LEN + OFFSETS_LEN && data[0] == 1, ErrorCode::InvalidDeviceSignature);
+
+    let read_u16 = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]);
+    let offsets = HEADER_LEN;
+    let signature_ix = read_u16(offsets + 2);
+    let public_key_offset = read_u16(offsets + 4) as usize;
+    let public_key_ix = read_u16(offsets + 6);
+    let message_offset = read_u16(offsets + 8) as usize;
+    let message_size = read_u16(offsets + 10) as usize;
+    let message_ix = read_u16(offsets + 12);
+
+    // Everything must live inside the ed25519 instruction itself
+    require!(
+        signature_ix == CURRENT_INSTRUCTION
+            && public_key_ix == CURRENT_INSTRUCTION
+            && message_ix == CURRENT_INSTRUCTION,
+        ErrorCode::InvalidDeviceSignature
+    );
+
+    let public_key = data
+        .get(public_key_offset..public_key_offset + 32)
+        .ok_or(ErrorCode::InvalidDeviceSignature)?;
+    let message = data
+        .get(message_offset..message_offset + message_size)
+        .ok_or(ErrorCode::InvalidDeviceSignature)?;
+    require!(message == expected_message, ErrorCode::InvalidDeviceSignature);
+
+    Pubkey::try_from(public_key).map_err(|_| error!(ErrorCode::InvalidDeviceSignature))
+}
+
+/// Score an emotion history as a weighted blend of average confidence,
+/// confidence-weighted VAD consistency and record volume (0.0 - 1.0)
+pub fn compute_quality_score(history: &[CompactEmotion], params: &QualityParams) -> f64 {
+    if history.is_empty() {
+        return 0.0;
+    }
+
+    let confidences: Vec<f64> = history.iter().map(|record| record.confidence_f64()).collect();
+    let total_confidence: f64 = confidences.iter().sum();
+    let average_confidence = total_confidence / history.len() as f64;
+
+    let consistency = if total_confidence > 0.0 {
+        let weighted_mean = |component: fn(&CompactEmotion) -> i16| {
+            history
+                .iter()
+                .zip(&confidences)
+                .map(|(record, weight)| from_fixed(component(record)) * weight)
+                .sum::<f64>()
+                / total_confidence
+        };
+        let mean = [
+            weighted_mean(|record| record.valence),
+            weighted_mean(|record| record.arousal),
+            weighted_mean(|record| record.dominance),
+        ];
+
+        // Confidence-weighted mean absolute deviation across the three VAD axes
+        let deviation = history
+            .iter()
+            .zip(&confidences)
+            .map(|(record, weight)| {
+                let distance = (from_fixed(record.valence) - mean[0]).abs()
+                    + (from_fixed(record.arousal) - mean[1]).abs()
+                    + (from_fixed(record.dominance) - mean[2]).abs();
+                distance / 3.0 * weight
+            })
+            .sum::<f64>()
+            / total_confidence;
+        1.0 - deviation.min(1.0)
+    } else {
+        0.0
+    };
+
+    let volume = (history.len() as f64 / params.target_record_count.max(1) as f64).min(1.0);
+
+    (params.confidence_weight_bps as f64 * average_confidence
+        + params.consistency_weight_bps as f64 * consistency
+        + params.volume_weight_bps as f64 * volume)
+        / BPS_DENOMINATOR as f64
+}
+
+/// Score how closely two emotion histories track each other, in basis points
+///
+/// The most recent COMPATIBILITY_WINDOW records of each history are paired
+/// newest-first and their L1 VAD distance averaged in fixed-point, so the
+/// result is deterministic across validators. Returns None if either history
+/// is empty.
+pub fn compatibility_score_bps(
+    history_a: &[CompactEmotion],
+    history_b: &[CompactEmotion],
+) -> Option<(u16, u8)> {
+    let samples = history_a.len().min(history_b.len()).min(COMPATIBILITY_WINDOW);
+    if samples == 0 {
+        return None;
+    }
+
+    let total_distance: i64 = history_a
+        .iter()
+        .rev()
+        .zip(history_b.iter().rev())
+        .take(samples)
+        .map(|(a, b)| {
+            (a.valence as i64 - b.valence as i64).abs()
+                + (a.arousal as i64 - b.arousal as i64).abs()
+                + (a.dominance as i64 - b.dominance as i64).abs()
+        })
+        .sum();
+
+    // Valence spans -1.0 - 1.0, arousal and dominance 0.0 - 1.0
+    let max_distance = 4 * VAD_SCALE as i64;
+    let average_distance = (total_distance / samples as i64).min(max_distance);
+    let score = BPS_DENOMINATOR as i64 - average_distance * BPS_DENOMINATOR as i64 / max_distance;
+
+    Some((score as u16, samples as u8))
+}
+
+/// Convert a pre-compact NFTAccount into the current layout, dropping its unsalted hash
+fn migrate_legacy_layout(legacy: LegacyNFTAccount, keep_emotion_history: bool) -> NFTAccount {
+    let emotion_history: Vec<CompactEmotion> = if keep_emotion_history {
+        let skip = legacy.emotion_history.len().saturating_sub(MAX_EMOTION_HISTORY);
+        legacy.emotion_history[skip..].iter().map(CompactEmotion::from).collect()
+    } else {
+        Vec::new()
+    };
+    let emotion_data = match emotion_history.last() {
+        Some(latest) => *latest,
+        None => CompactEmotion::erased(),
+    };
+
+    NFTAccount {
+        owner: legacy.owner,
+        emotion_data,
+        quality_score: legacy.quality_score,
+        biometric_hash: String::new(),
+        is_verified: false,
+        created_at: legacy.created_at,
+        emotion_history,
+        biometric_salt: [0u8; 32],
+        hash_version: HASH_VERSION_UNENROLLED,
+        pending_commitment: [0u8; 32],
+        commitment_verifier: Pubkey::default(),
+        committed_at: 0,
+        committed_slot: 0,
+        verified_by: Pubkey::default(),
+        verified_at: 0,
+        transfer_policy: TransferPolicy::Locked,
+        unlock_approval: None,
+        mint: Pubkey::default(),
+        consent_flags: if keep_emotion_history { CONSENT_EMOTION_HISTORY } else { 0 },
+        zk_nonce: 0,
+    }
+}
+
+/// Check every soulbound transfer rule without mutating the account
+fn authorize_transfer(
+    nft_account: &NFTAccount,
+    current_owner: &Pubkey,
+    new_owner: &Pubkey,
+    now: i64,
+) -> std::result::Result<(), ErrorCode> {
+    if nft_account.owner != *current_owner {
+        return Err(ErrorCode::Unauthorized);
+    }
+    if !nft_account.is_verified {
+        return Err(ErrorCode::NotVerified);
+    }
+    if *new_owner == nft_account.owner {
+        return Err(ErrorCode::InvalidTransfer);
+    }
+    if nft_account.transfer_policy == TransferPolicy::Locked {
+        return Err(ErrorCode::TransferLocked);
+    }
+
+    let approval = nft_account.unlock_approval.as_ref().ok_or(ErrorCode::UnlockNotApproved)?;
+    if approval.new_owner != *new_owner {
+        return Err(ErrorCode::UnlockNotApproved);
+    }
+    if now - approval.approved_at > UNLOCK_VALIDITY_SECONDS {
+        return Err(ErrorCode::UnlockExpired);
+    }
+
+    Ok(())
+}
+
+/// Check that a minted NFT's transfer carries its token accounts, without mutating them
+fn check_token_accounts(accounts: &TransferNFT, new_owner: &Pubkey) -> std::result::Result<(), ErrorCode> {
+    if accounts.nft_account.mint == Pubkey::default() {
+        return Ok(());
+    }
+
+    let (Some(mint), Some(source), Some(destination), Some(_), Some(_)) = (
+        &accounts.mint,
+        &accounts.source_token_account,
+        &accounts.destination_token_account,
+        &accounts.mint_authority,
+        &accounts.token_program,
+    ) else {
+        return Err(ErrorCode::MissingTokenAccounts);
+    };
+
+    if mint.key() != accounts.nft_account.mint {
+        return Err(ErrorCode::InvalidTokenAccount);
+    }
+    if source.mint != mint.key() || source.owner != accounts.current_owner.key() || source.amount != 1 {
+        return Err(ErrorCode::InvalidTokenAccount);
+    }
+    if destination.key() != get_associated_token_address(new_owner, &mint.key()) {
+        return Err(ErrorCode::InvalidTokenAccount);
+    }
+
+    Ok(())
+}
+
+/// Thaw the current owner's token, move it to the new owner's ATA and freeze it there
+fn move_soulbound_token(accounts: &TransferNFT, mint_authority_bump: u8) -> Result<()> {
+    let (Some(mint), Some(source), Some(destination), Some(mint_authority), Some(token_program)) = (
+        &accounts.mint,
+        &accounts.source_token_account,
+        &accounts.destination_token_account,
+        &accounts.mint_authority,
+        &accounts.token_program,
+    ) else {
+        return err!(ErrorCode::MissingTokenAccounts);
+    };
+
+    let signer_seeds: &[&[&[u8]]] = &[&[MINT_AUTHORITY_SEED, &[mint_authority_bump]]];
+    let token_program = token_program.to_account_info();
+
+    token::thaw_account(CpiContext::new_with_signer(
+        token_program.clone(),
+        ThawAccount {
+            account: source.to_account_info(),
+            mint: mint.to_account_info(),
+            authority: mint_authority.to_account_info(),
+        },
+        signer_seeds,
+    ))?;
+
+    token::transfer(
+        CpiContext::new(
+            token_program.clone(),
+            Transfer {
+                from: source.to_account_info(),
+                to: destination.to_account_info(),
+                authority: accounts.current_owner.to_account_info(),
+            },
+        ),
+        1,
+    )?;
+
+    token::freeze_account(CpiContext::new_with_signer(
+        token_program,
+        FreezeAccount {
+            account: destination.to_account_info(),
+            mint: mint.to_account_info(),
+            authority: mint_authority.to_account_info(),
+        },
+        signer_seeds,
+    ))
+}
+
+/// Check that a string is a 64 character lowercase hex SHA-256 digest
+fn is_hex_digest(value: &str) -> bool {
+    value.len() == 64 && value.bytes().all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    const HEX: &[u8; 16] = b"0123456789abcdef";
+    let mut out = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        out.push(HEX[(byte >> 4) as usize] as char);
+        out.push(HEX[(byte & 0x0f) as usize] as char);
+    }
+    out
+}
+
+/// Accounts for initializing a new NFT
+#[derive(Accounts)]
+pub struct InitializeNFT<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = NFTAccount::SPACE
+    )]
+    pub nft_account: Account<'info, NFTAccount>,
+    
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for minting the SPL token and Metaplex metadata of an NFT
+#[derive(Accounts)]
+pub struct MintNFTToken<'info> {
+    #[account(mut)]
+    pub nft_account: Account<'info, NFTAccount>,
+    
+    #[account(
+        init,
+        payer = owner,
+        mint::decimals = 0,
+        mint::authority = mint_authority,
+        mint::freeze_authority = mint_authority,
+        seeds = [MINT_SEED, nft_account.key().as_ref()],
+        bump
+    )]
+    pub mint: Box<Account<'info, Mint>>,
+    
+    #[account(
+        init,
+        payer = owner,
+        associated_token::mint = mint,
+        associated_token::authority = owner
+    )]
+    pub token_account: Box<Account<'info, TokenAccount>>,
+    
+    /// CHECK: PDA that signs as mint, freeze and update authority
+    #[account(seeds = [MINT_AUTHORITY_SEED], bump)]
+    pub mint_authority: UncheckedAccount<'info>,
+    
+    /// CHECK: Metadata PDA, derived and validated by the token metadata program
+    #[account(
+        mut,
+        seeds = [b"metadata", token_metadata_program.key().as_ref(), mint.key().as_ref()],
+        bump,
+        seeds::program = token_metadata_program.key()
+    )]
+    pub metadata: UncheckedAccount<'info>,
+    
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    
+    pub token_program: Program<'info, Token>,
+    
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    
+    pub token_metadata_program: Program<'info, Metadata>,
+    
+    pub system_program: Program<'info, System>,
+    
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/// Accounts for creating the verifier registry
+#[derive(Accounts)]
+pub struct InitializeVerifierRegistry<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = VerifierRegistry::SPACE,
+        seeds = [VERIFIER_REGISTRY_SEED],
+        bump
+    )]
+    pub verifier_registry: Account<'info, VerifierRegistry>,
+    
+    /// Only the program's upgrade authority may become the registry admin
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, crate::program::BiometricNft>,
+    
+    #[account(constraint = program_data.upgrade_authority_address == Some(admin.key()) @ ErrorCode::Unauthorized)]
+    pub program_data: Account<'info, ProgramData>,
+    
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for creating the program config
+#[derive(Accounts)]
+pub struct InitializeProgramConfig<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = ProgramConfig::SPACE,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+    
+    /// Only the program's upgrade authority may become the config admin
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, crate::program::BiometricNft>,
+    
+    #[account(constraint = program_data.upgrade_authority_address == Some(admin.key()) @ ErrorCode::Unauthorized)]
+    pub program_data: Account<'info, ProgramData>,
+    
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for updating the program config
+#[derive(Accounts)]
+pub struct UpdateProgramConfig<'info> {
+    #[account(mut, seeds = [PROGRAM_CONFIG_SEED], bump = program_config.bump)]
+    pub program_config: Account<'info, ProgramConfig>,
+    
+    pub admin: Signer<'info>,
+}
+
+/// Accounts for adding or removing registered verifiers
+#[derive(Accounts)]
+pub struct ManageVerifiers<'info> {
+    #[account(mut, seeds = [VERIFIER_REGISTRY_SEED], bump = verifier_registry.bump)]
+    pub verifier_registry: Account<'info, VerifierRegistry>,
+    
+    pub admin: Signer<'info>,
+}
+
+/// Accounts for committing to a biometric verification
+#[derive(Accounts)]
+pub struct CommitBiometric<'info> {
+    #[account(mut)]
+    pub nft_account: Account<'info, NFTAccount>,
+    
+    #[account(seeds = [VERIFIER_REGISTRY_SEED], bump = verifier_registry.bump)]
+    pub verifier_registry: Account<'info, VerifierRegistry>,
+    
+    pub verifier: Signer<'info>,
+}
+
+/// Accounts for verifying biometric data
+#[derive(Accounts)]
+pub struct VerifyBiometric<'info> {
+    #[account(mut)]
+    pub nft_account: Account<'info, NFTAccount>,
+    
+    #[account(seeds = [VERIFIER_REGISTRY_SEED], bump = verifier_registry.bump)]
+    pub verifier_registry: Account<'info, VerifierRegistry>,
+    
+    pub verifier: Signer<'info>,
+}
+
+/// Accounts for storing the Groth16 verifying key
+#[derive(Accounts)]
+pub struct InitializeZkVerifyingKey<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = ZkVerifyingKey::SPACE,
+        seeds = [ZK_VERIFYING_KEY_SEED],
+        bump
+    )]
+    pub zk_verifying_key: Account<'info, ZkVerifyingKey>,
+    
+    #[account(seeds = [PROGRAM_CONFIG_SEED], bump = program_config.bump)]
+    pub program_config: Account<'info, ProgramConfig>,
+    
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for replacing the Groth16 verifying key
+#[derive(Accounts)]
+pub struct UpdateZkVerifyingKey<'info> {
+    #[account(mut, seeds = [ZK_VERIFYING_KEY_SEED], bump = zk_verifying_key.bump)]
+    pub zk_verifying_key: Account<'info, ZkVerifyingKey>,
+    
+    #[account(seeds = [PROGRAM_CONFIG_SEED], bump = program_config.bump)]
+    pub program_config: Account<'info, ProgramConfig>,
+    
+    pub admin: Signer<'info>,
+}
+
+/// Accounts for zero-knowledge biometric verification
+#[derive(Accounts)]
+pub struct VerifyBiometricZk<'info> {
+    #[account(mut)]
+    pub nft_account: Account<'info, NFTAccount>,
+    
+    #[account(seeds = [ZK_VERIFYING_KEY_SEED], bump = zk_verifying_key.bump)]
+    pub zk_verifying_key: Account<'info, ZkVerifyingKey>,
+    
+    pub owner: Signer<'info>,
+}
+
+/// Accounts for updating emotion data
+#[derive(Accounts)]
+pub struct UpdateEmotion<'info> {
+    #[account(mut)]
+    pub nft_account: Account<'info, NFTAccount>,
+    
+    pub owner: Signer<'info>,
+    
+    /// Device registry, only for device-attested updates
+    #[account(seeds = [DEVICE_REGISTRY_SEED], bump = device_registry.bump)]
+    pub device_registry: Option<Account<'info, DeviceRegistry>>,
+    
+    /// CHECK: Instructions sysvar, only for device-attested updates
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: Option<UncheckedAccount<'info>>,
+}
+
+/// Accounts for batch and delta emotion updates
+#[derive(Accounts)]
+pub struct UpdateEmotionRecords<'info> {
+    #[account(mut)]
+    pub nft_account: Account<'info, NFTAccount>,
+    
+    pub owner: Signer<'info>,
+}
+
+/// Accounts for creating the device registry
+#[derive(Accounts)]
+pub struct InitializeDeviceRegistry<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = DeviceRegistry::SPACE,
+        seeds = [DEVICE_REGISTRY_SEED],
+        bump
+    )]
+    pub device_registry: Account<'info, DeviceRegistry>,
+    
+    #[account(seeds = [PROGRAM_CONFIG_SEED], bump = program_config.bump)]
+    pub program_config: Account<'info, ProgramConfig>,
+    
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for managing vendors and devices (admin or vendor signs)
+#[derive(Accounts)]
+pub struct ManageDevices<'info> {
+    #[account(mut, seeds = [DEVICE_REGISTRY_SEED], bump = device_registry.bump)]
+    pub device_registry: Account<'info, DeviceRegistry>,
+    
+    pub authority: Signer<'info>,
+}
+
+/// Accounts for recomputing an NFT's quality score
+#[derive(Accounts)]
+pub struct RecomputeQuality<'info> {
+    #[account(mut)]
+    pub nft_account: Account<'info, NFTAccount>,
+    
+    #[account(seeds = [VERIFIER_REGISTRY_SEED], bump = verifier_registry.bump)]
+    pub verifier_registry: Account<'info, VerifierRegistry>,
+    
+    #[account(seeds = [PROGRAM_CONFIG_SEED], bump = program_config.bump)]
+    pub program_config: Account<'info, ProgramConfig>,
+    
+    pub verifier: Signer<'info>,
+}
+
+/// Accounts for computing a compatibility score (nft_a must sort before nft_b)
+#[derive(Accounts)]
+pub struct ComputeCompatibility<'info> {
+    #[account(
+        init_if_needed,
+        payer = requester,
+        space = Compatibility::SPACE,
+        seeds = [COMPATIBILITY_SEED, nft_a.key().as_ref(), nft_b.key().as_ref()],
+        bump
+    )]
+    pub compatibility: Account<'info, Compatibility>,
+    
+    pub nft_a: Account<'info, NFTAccount>,
+    
+    pub nft_b: Account<'info, NFTAccount>,
+    
+    #[account(mut)]
+    pub requester: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for approving a soulbound unlock
+#[derive(Accounts)]
+pub struct RequestUnlock<'info> {
+    #[account(mut)]
+    pub nft_account: Account<'info, NFTAccount>,
+    
+    #[account(seeds = [VERIFIER_REGISTRY_SEED], bump = verifier_registry.bump)]
+    pub verifier_registry: Account<'info, VerifierRegistry>,
+    
+    pub owner: Signer<'info>,
+    
+    pub attester: Signer<'info>,
+}
+
+/// Accounts for transferring NFT (token accounts are required once a token is minted)
+#[derive(Accounts)]
+pub struct TransferNFT<'info> {
+    #[account(mut)]
+    pub nft_account: Account<'info, NFTAccount>,
+    
+    #[account(mut)]
+    pub current_owner: Signer<'info>,
+    
+    pub mint: Option<Box<Account<'info, Mint>>>,
+    
+    #[account(mut)]
+    pub source_token_account: Option<Box<Account<'info, TokenAccount>>>,
+    
+    /// New owner's associated token account, created beforehand by the client
+    #[account(mut)]
+    pub destination_token_account: Option<Box<Account<'info, TokenAccount>>>,
+    
+    /// CHECK: PDA that signs as freeze authority
+    #[account(seeds = [MINT_AUTHORITY_SEED], bump)]
+    pub mint_authority: Option<UncheckedAccount<'info>>,
+    
+    pub token_program: Option<Program<'info, Token>>,
+}
+
+/// Accounts for revoking biometric consent
+#[derive(Accounts)]
+pub struct RevokeBiometricConsent<'info> {
+    #[account(mut)]
+    pub nft_account: Account<'info, NFTAccount>,
+    
+    pub owner: Signer<'info>,
+}
+
+/// Accounts for enrolling a salted biometric hash
+#[derive(Accounts)]
+pub struct EnrollBiometricHash<'info> {
+    #[account(mut)]
+    pub nft_account: Account<'info, NFTAccount>,
+    
+    pub owner: Signer<'info>,
+}
+
+/// Accounts for migrating a pre-compact NFTAccount
+#[derive(Accounts)]
+pub struct MigrateLegacyAccount<'info> {
+    /// CHECK: Decoded by hand because the legacy layout no longer matches NFTAccount
+    #[account(mut, owner = crate::ID)]
+    pub nft_account: UncheckedAccount<'info>,
+    
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
 }
 
 /// Main NFT account structure
 #[account]
-pub struct NFTAccount {
-    pub owner: Pubkey,                    // 32 bytes
-    pub emotion_data: EmotionData,        // Serialized emotion data
-    pub quality_score: f64,               // 8 bytes
-    pub biometric_hash: String,           // Dynamic - biometric hash
-    pub is_verified: bool,                // 1 byte
-    pub created_at: i64,                   // 8 bytes
-    pub emotion_history: Vec<EmotionData>, // Dynamic - historical emotion data
+pub struct NFTAccount {
+    pub owner: Pubkey,                    // 32 bytes
+    pub emotion_data: CompactEmotion,     // CompactEmotion::SIZE bytes - latest emotion
+    pub quality_score: f64,               // 8 bytes
+    pub biometric_hash: String,           // Dynamic - biometric hash
+    pub is_verified: bool,                // 1 byte
+    pub created_at: i64,                   // 8 bytes
+    pub emotion_history: Vec<CompactEmotion>, // 4 + CompactEmotion::SIZE * MAX_EMOTION_HISTORY bytes
+    pub biometric_salt: [u8; 32],         // 32 bytes - per-NFT hash salt
+    pub hash_version: u8,                 // 1 byte - biometric hash format
+    pub pending_commitment: [u8; 32],     // 32 bytes - outstanding verification commitment
+    pub commitment_verifier: Pubkey,      // 32 bytes - verifier that committed
+    pub committed_at: i64,                // 8 bytes - commitment timestamp
+    pub committed_slot: u64,              // 8 bytes - commitment slot
+    pub verified_by: Pubkey,              // 32 bytes - verifier that attested
+    pub verified_at: i64,                 // 8 bytes - attestation timestamp
+    pub transfer_policy: TransferPolicy,  // 1 byte - soulbound unlock rules
+    pub unlock_approval: Option<UnlockApproval>, // 1 + 72 bytes - pending approved unlock
+    pub mint: Pubkey,                     // 32 bytes - SPL mint, default until minted
+    pub consent_flags: u8,                // 1 byte - CONSENT_* bits granted by the owner
+    pub zk_nonce: u64,                    // 8 bytes - successful ZK verifications, bound into each proof
+}
+
+impl NFTAccount {
+    pub const SPACE: usize = 8 // discriminator
+        + 32 // owner
+        + CompactEmotion::SIZE // emotion_data
+        + 8 // quality_score
+        + 4 + 64 // biometric_hash (hex SHA-256)
+        + 1 // is_verified
+        + 8 // created_at
+        + 4 + CompactEmotion::SIZE * MAX_EMOTION_HISTORY // emotion_history
+        + 32 // biometric_salt
+        + 1 // hash_version
+        + 32 // pending_commitment
+        + 32 // commitment_verifier
+        + 8 // committed_at
+        + 8 // committed_slot
+        + 32 // verified_by
+        + 8 // verified_at
+        + 1 // transfer_policy
+        + 1 + 72 // unlock_approval
+        + 32 // mint
+        + 1 // consent_flags
+        + 8; // zk_nonce
+
+    pub fn has_consent(&self, flag: u8) -> bool {
+        self.consent_flags & flag == flag
+    }
+
+    /// Append records, make the last one current and trim history to MAX_EMOTION_HISTORY
+    pub fn record_emotions(&mut self, emotions: &[CompactEmotion]) {
+        let Some(latest) = emotions.last() else {
+            return;
+        };
+
+        self.emotion_data = *latest;
+        self.emotion_history.extend_from_slice(emotions);
+
+        // Keep only the most recent records so the account never outgrows its allocation
+        if self.emotion_history.len() > MAX_EMOTION_HISTORY {
+            let excess = self.emotion_history.len() - MAX_EMOTION_HISTORY;
+            self.emotion_history.drain(..excess);
+        }
+    }
+}
+
+/// How a soulbound NFT may be unlocked for transfer
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransferPolicy {
+    /// Never transferable
+    Locked,
+    /// Transferable once the registry admin approves an unlock
+    UnlockableByDAO,
+    /// Transferable once a registered verifier attests the owner's key is compromised
+    CompromisedRecovery,
+}
+
+/// Approved one-time unlock for a specific new owner
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct UnlockApproval {
+    pub new_owner: Pubkey,
+    pub approved_by: Pubkey,
+    pub approved_at: i64,
+}
+
+/// Compatibility score between two NFTs, keyed by the ordered pair
+#[account]
+pub struct Compatibility {
+    pub nft_a: Pubkey,                    // 32 bytes
+    pub nft_b: Pubkey,                    // 32 bytes
+    pub score_bps: u16,                   // 2 bytes - 10000 = identical histories
+    pub samples: u8,                      // 1 byte - records compared
+    pub computed_at: i64,                 // 8 bytes
+    pub bump: u8,                         // 1 byte
+}
+
+impl Compatibility {
+    pub const SPACE: usize = 8 + 32 + 32 + 2 + 1 + 8 + 1;
+}
+
+/// Program-wide tunable parameters
+#[account]
+pub struct ProgramConfig {
+    pub admin: Pubkey,                    // 32 bytes
+    pub quality_params: QualityParams,    // 8 bytes
+    pub bump: u8,                         // 1 byte
+}
+
+impl ProgramConfig {
+    pub const SPACE: usize = 8 + 32 + QualityParams::SIZE + 1;
+}
+
+/// Weights for recompute_quality, in basis points summing to BPS_DENOMINATOR
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub struct QualityParams {
+    pub confidence_weight_bps: u16,
+    pub consistency_weight_bps: u16,
+    pub volume_weight_bps: u16,
+    pub target_record_count: u16,         // Records needed for full volume credit
+}
+
+impl QualityParams {
+    pub const SIZE: usize = 2 + 2 + 2 + 2;
+
+    pub fn is_valid(&self) -> bool {
+        let total = self.confidence_weight_bps as u32
+            + self.consistency_weight_bps as u32
+            + self.volume_weight_bps as u32;
+        total == BPS_DENOMINATOR as u32 && self.target_record_count > 0
+    }
+}
+
+/// Groth16 verifying key for the biometric match circuit
+#[account]
+pub struct ZkVerifyingKey {
+    pub verifying_key: Groth16VerifyingKey, // 832 bytes
+    pub bump: u8,                         // 1 byte
+}
+
+impl ZkVerifyingKey {
+    pub const SPACE: usize = 8 + Groth16VerifyingKey::SIZE + 1;
+}
+
+/// Big-endian alt_bn128 encoded verifying key (G1 = 64 bytes, G2 = 128 bytes)
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct Groth16VerifyingKey {
+    pub alpha_g1: [u8; 64],
+    pub beta_g2: [u8; 128],
+    pub gamma_g2: [u8; 128],
+    pub delta_g2: [u8; 128],
+    pub ic: [[u8; 64]; ZK_PUBLIC_INPUTS + 1],
+}
+
+impl Groth16VerifyingKey {
+    pub const SIZE: usize = 64 + 128 * 3 + 64 * (ZK_PUBLIC_INPUTS + 1);
+}
+
+/// Big-endian alt_bn128 encoded Groth16 proof (A is not pre-negated)
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct Groth16Proof {
+    pub a: [u8; 64],
+    pub b: [u8; 128],
+    pub c: [u8; 64],
+}
+
+/// Registry of hardware vendors and the device keys they vouch for
+#[account]
+pub struct DeviceRegistry {
+    pub admin: Pubkey,                    // 32 bytes
+    pub vendors: Vec<Pubkey>,             // Bounded by MAX_DEVICE_VENDORS
+    pub devices: Vec<RegisteredDevice>,   // Bounded by MAX_DEVICES
+    pub bump: u8,                         // 1 byte
+}
+
+impl DeviceRegistry {
+    pub const SPACE: usize = 8 + 32 + 4 + 32 * MAX_DEVICE_VENDORS + 4 + 64 * MAX_DEVICES + 1;
+
+    pub fn is_registered_device(&self, device: &Pubkey) -> bool {
+        self.devices.iter().any(|registered| registered.device == *device)
+    }
+}
+
+/// Device signing key and the vendor that registered it
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct RegisteredDevice {
+    pub device: Pubkey,
+    pub vendor: Pubkey,
+}
+
+/// Registry of verifiers allowed to attest biometric verifications
+#[account]
+pub struct VerifierRegistry {
+    pub admin: Pubkey,                    // 32 bytes
+    pub verifiers: Vec<Pubkey>,           // Bounded by MAX_VERIFIERS
+    pub bump: u8,                         // 1 byte
+}
+
+impl VerifierRegistry {
+    pub const SPACE: usize = 8 + 32 + 4 + 32 * MAX_VERIFIERS + 1;
+
+    pub fn is_registered(&self, verifier: &Pubkey) -> bool {
+        self.verifiers.contains(verifier)
+    }
+}
+
+/// Compact on-chain emotion record
+///
+/// VAD values are fixed-point with `VAD_SCALE` units per 1.0, confidence maps
+/// 0.0 - 1.0 onto 0 - 255, and secondary emotions stay off-chain.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CompactEmotion {
+    pub primary_emotion: Emotion,         // 1 - 2 bytes
+    pub confidence: u8,                   // Confidence scaled to 0 - 255
+    pub valence: i16,                     // Fixed-point valence (-1.0 to 1.0)
+    pub arousal: i16,                     // Fixed-point arousal (0.0 to 1.0)
+    pub dominance: i16,                   // Fixed-point dominance (0.0 to 1.0)
+    pub timestamp: i64,                   // Unix timestamp
+    pub device_attested: bool,            // Signed by a registered device
+}
+
+impl CompactEmotion {
+    pub const SIZE: usize = Emotion::MAX_SIZE + 1 + 2 + 2 + 2 + 8 + 1;
+
+    /// Placeholder left behind when consent is revoked
+    pub fn erased() -> Self {
+        Self {
+            primary_emotion: Emotion::Neutral,
+            confidence: 0,
+            valence: 0,
+            arousal: 0,
+            dominance: 0,
+            timestamp: 0,
+            device_attested: false,
+        }
+    }
+
+    /// Check each VAD axis against the range `to_fixed` clamps client floats to
+    pub fn is_in_range(&self) -> bool {
+        let scale = VAD_SCALE as i16;
+        (-scale..=scale).contains(&self.valence)
+            && (0..=scale).contains(&self.arousal)
+            && (0..=scale).contains(&self.dominance)
+    }
+
+    pub fn confidence_f64(&self) -> f64 {
+        self.confidence as f64 / 255.0
+    }
+
+    /// Apply a delta exactly as update_emotion_delta does on-chain
+    ///
+    /// Clients reconstruct the emotion stream by starting from the last full
+    /// record (initialize_nft, update_emotion or update_emotions_batch) and
+    /// folding each subsequent EmotionDelta through this function in order.
+    pub fn apply_delta(&self, delta: &EmotionDelta) -> Self {
+        let step = |value: i16, change: i8, min: i16| {
+            (value as i32 + change as i32 * DELTA_STEP as i32)
+                .clamp(min as i32, VAD_SCALE as i32) as i16
+        };
+
+        Self {
+            primary_emotion: delta.primary_emotion.unwrap_or(self.primary_emotion),
+            confidence: (self.confidence as i16 + delta.confidence as i16).clamp(0, u8::MAX as i16) as u8,
+            valence: step(self.valence, delta.valence, -(VAD_SCALE as i16)),
+            arousal: step(self.arousal, delta.arousal, 0),
+            dominance: step(self.dominance, delta.dominance, 0),
+            timestamp: self.timestamp.saturating_add(delta.elapsed_seconds as i64),
+            device_attested: false,
+        }
+    }
+}
+
+/// Signed change against the latest stored CompactEmotion
+///
+/// VAD deltas are in units of DELTA_STEP fixed-point steps (0.01), so one
+/// update can move a component by -1.28 to +1.27 before clamping; confidence
+/// deltas are raw 0 - 255 steps.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct EmotionDelta {
+    pub valence: i8,
+    pub arousal: i8,
+    pub dominance: i8,
+    pub confidence: i8,
+    pub elapsed_seconds: u32,             // Seconds since the previous record
+    pub primary_emotion: Option<Emotion>, // None keeps the previous emotion
+}
+
+/// NFTAccount layout used before CompactEmotion, read only by migrate_legacy_account
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct LegacyNFTAccount {
+    pub owner: Pubkey,
+    pub emotion_data: LegacyEmotionData,
+    pub quality_score: f64,
+    pub biometric_hash: String,           // Unsalted SHA-256 hex
+    pub is_verified: bool,
+    pub created_at: i64,
+    pub emotion_history: Vec<LegacyEmotionData>,
+}
+
+/// Emotion record stored by legacy accounts
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct LegacyEmotionData {
+    pub primary_emotion: String,
+    pub confidence: f64,
+    pub secondary_emotions: Vec<LegacySecondaryEmotion>,
+    pub valence: f64,
+    pub arousal: f64,
+    pub dominance: f64,
+    pub timestamp: i64,
+}
+
+/// Secondary emotion stored by legacy accounts
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct LegacySecondaryEmotion {
+    pub emotion: String,
+    pub score: f64,
+}
+
+impl From<&LegacyEmotionData> for CompactEmotion {
+    fn from(data: &LegacyEmotionData) -> Self {
+        let primary_emotion = Emotion::from_label(&data.primary_emotion)
+            .unwrap_or_else(|| Emotion::from_vad(data.valence, data.arousal, data.dominance));
+
+        Self {
+            primary_emotion,
+            confidence: (data.confidence.clamp(0.0, 1.0) * 255.0).round() as u8,
+            valence: to_fixed(data.valence, -1.0),
+            arousal: to_fixed(data.arousal, 0.0),
+            dominance: to_fixed(data.dominance, 0.0),
+            timestamp: data.timestamp,
+            device_attested: false,
+        }
+    }
+}
+
+impl From<&EmotionData> for CompactEmotion {
+    fn from(data: &EmotionData) -> Self {
+        Self {
+            primary_emotion: data.primary_emotion,
+            confidence: (data.confidence.clamp(0.0, 1.0) * 255.0).round() as u8,
+            valence: to_fixed(data.valence, -1.0),
+            arousal: to_fixed(data.arousal, 0.0),
+            dominance: to_fixed(data.dominance, 0.0),
+            timestamp: data.timestamp,
+            device_attested: false,
+        }
+    }
+}
+
+impl From<&CompactEmotion> for EmotionData {
+    fn from(compact: &CompactEmotion) -> Self {
+        Self {
+            primary_emotion: compact.primary_emotion,
+            confidence: compact.confidence_f64(),
+            secondary_emotions: Vec::new(),
+            valence: from_fixed(compact.valence),
+            arousal: from_fixed(compact.arousal),
+            dominance: from_fixed(compact.dominance),
+            timestamp: compact.timestamp,
+        }
+    }
+}
+
+/// Fixed-point units per 1.0 for on-chain VAD values
+pub const VAD_SCALE: f64 = 10_000.0;
+/// Fixed-point units per EmotionDelta step (0.01)
+pub const DELTA_STEP: i16 = 100;
+
+/// Canonical emotion categories shared by the program and its clients
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Emotion {
+    Neutral,
+    Joy,
+    Sadness,
+    Anger,
+    Fear,
+    Surprise,
+    Disgust,
+    Trust,
+    /// Escape hatch for categories outside this list, keyed by an off-chain code
+    Other(u8),
+}
+
+impl Emotion {
+    /// Borsh size of the largest variant (tag + Other payload)
+    pub const MAX_SIZE: usize = 2;
+
+    /// Parse a free-form label, accepting common synonyms and any casing
+    pub fn from_label(label: &str) -> Option<Self> {
+        let emotion = match label.trim().to_ascii_lowercase().as_str() {
+            "neutral" | "calm" => Emotion::Neutral,
+            "joy" | "happy" | "happiness" | "excited" => Emotion::Joy,
+            "sadness" | "sad" => Emotion::Sadness,
+            "anger" | "angry" => Emotion::Anger,
+            "fear" | "afraid" | "anxious" => Emotion::Fear,
+            "surprise" | "surprised" => Emotion::Surprise,
+            "disgust" | "disgusted" => Emotion::Disgust,
+            "trust" | "content" | "relaxed" => Emotion::Trust,
+            _ => return None,
+        };
+        Some(emotion)
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Emotion::Neutral => "neutral",
+            Emotion::Joy => "joy",
+            Emotion::Sadness => "sadness",
+            Emotion::Anger => "anger",
+            Emotion::Fear => "fear",
+            Emotion::Surprise => "surprise",
+            Emotion::Disgust => "disgust",
+            Emotion::Trust => "trust",
+            Emotion::Other(_) => "other",
+        }
+    }
+
+    /// Categorize a VAD point (valence -1.0 - 1.0, arousal/dominance 0.0 - 1.0)
+    pub fn from_vad(valence: f64, arousal: f64, dominance: f64) -> Self {
+        const VALENCE_DEADBAND: f64 = 0.1;
+
+        if valence > VALENCE_DEADBAND {
+            if arousal >= 0.5 {
+                Emotion::Joy
+            } else {
+                Emotion::Trust
+            }
+        } else if valence < -VALENCE_DEADBAND {
+            match (arousal >= 0.5, dominance >= 0.5) {
+                (true, true) => Emotion::Anger,
+                (true, false) => Emotion::Fear,
+                (false, true) => Emotion::Disgust,
+                (false, false) => Emotion::Sadness,
+            }
+        } else if arousal >= 0.7 {
+            Emotion::Surprise
+        } else {
+            Emotion::Neutral
+        }
+    }
+}
+
+/// Convert a VAD component to fixed-point, clamping to `min` - 1.0
+///
+/// Valence uses a `min` of -1.0, arousal and dominance 0.0.
+pub fn to_fixed(value: f64, min: f64) -> i16 {
+    (value.clamp(min, 1.0) * VAD_SCALE).round() as i16
+}
+
+/// Convert a fixed-point VAD component back to a float
+pub fn from_fixed(value: i16) -> f64 {
+    value as f64 / VAD_SCALE
 }
 
-/// Emotion data structure
+/// Emotion data supplied by clients
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
 pub struct EmotionData {
-    pub primary_emotion: String,          // Primary emotion detected
+    pub primary_emotion: Emotion,         // Primary emotion detected
     pub confidence: f64,                    // AI confidence score (0.0 - 1.0)
     pub secondary_emotions: Vec<SecondaryEmotion>, // Secondary emotions with scores
     pub valence: f64,                     // Emotional valence (-1.0 to 1.0)
@@ -181,17 +2239,108 @@ pub struct EmotionData {
 /// Secondary emotion with score
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
 pub struct SecondaryEmotion {
-    pub emotion: String,
+    pub emotion: Emotion,
     pub score: f64,
 }
 
+/// Emitted when a biometric NFT is minted
+#[event]
+pub struct NFTInitialized {
+    pub nft: Pubkey,
+    pub owner: Pubkey,
+    pub primary_emotion: Emotion,
+    pub quality_score: f64,
+    pub transfer_policy: TransferPolicy,
+    pub timestamp: i64,
+}
+
+/// Emitted when the SPL token and metadata for an NFT are minted
+#[event]
+pub struct NFTTokenMinted {
+    pub nft: Pubkey,
+    pub owner: Pubkey,
+    pub mint: Pubkey,
+    pub metadata: Pubkey,
+}
+
+/// Emitted when a registered verifier attests a biometric match
+#[event]
+pub struct BiometricVerified {
+    pub nft: Pubkey,
+    pub owner: Pubkey,
+    pub verifier: Pubkey,
+    pub timestamp: i64,
+}
+
+/// Emitted for every accepted emotion update
+#[event]
+pub struct EmotionUpdated {
+    pub nft: Pubkey,
+    pub owner: Pubkey,
+    pub emotion: CompactEmotion,
+    pub history_len: u16,
+}
+
+/// Emitted when a batch of offline emotion samples is applied
+#[event]
+pub struct EmotionBatchUpdated {
+    pub nft: Pubkey,
+    pub owner: Pubkey,
+    pub count: u8,
+    pub first_timestamp: i64,
+    pub latest: CompactEmotion,
+    pub history_len: u16,
+}
+
+/// Emitted when a verifier recomputes an NFT's quality score
+#[event]
+pub struct QualityRecomputed {
+    pub nft: Pubkey,
+    pub verifier: Pubkey,
+    pub previous_score: f64,
+    pub quality_score: f64,
+    pub record_count: u16,
+    pub timestamp: i64,
+}
+
+/// Emitted when an owner revokes consent and their biometric data is erased
+#[event]
+pub struct ConsentRevoked {
+    pub nft: Pubkey,
+    pub owner: Pubkey,
+    pub timestamp: i64,
+}
+
+/// Emitted when a compatibility score is computed or refreshed
+#[event]
+pub struct CompatibilityComputed {
+    pub compatibility: Pubkey,
+    pub nft_a: Pubkey,
+    pub nft_b: Pubkey,
+    pub score_bps: u16,
+    pub samples: u8,
+    pub timestamp: i64,
+}
+
+/// Emitted for every transfer attempt, including refused ones
+#[event]
+pub struct TransferAttempted {
+    pub nft: Pubkey,
+    pub from: Pubkey,
+    pub to: Pubkey,
+    pub success: bool,
+    pub failure_code: Option<u32>,        // Anchor error code (6000 + variant)
+    pub failure_reason: Option<String>,   // Error message for the failure code
+    pub timestamp: i64,
+}
+
 /// Error codes
 #[error_code]
 pub enum ErrorCode {
     #[msg("Quality score is too low - minimum 0.7 required")]
     LowQualityScore,
     
-    #[msg("Biometric hash is invalid - must be 64 characters")]
+    #[msg("Biometric hash is invalid - must be a 64 character lowercase hex SHA-256 digest")]
     InvalidBiometricHash,
     
     #[msg("Biometric verification failed")]
@@ -208,4 +2357,570 @@ pub enum ErrorCode {
     
     #[msg("Invalid transfer - cannot transfer to same owner")]
     InvalidTransfer,
-}
\ No newline at end of file
+    
+    #[msg("Biometric salt must not be all zeros")]
+    InvalidSalt,
+    
+    #[msg("No salted biometric hash is enrolled - re-enrollment required")]
+    EnrollmentRequired,
+    
+    #[msg("A salted biometric hash is already enrolled")]
+    AlreadyEnrolled,
+    
+    #[msg("No pending biometric commitment - commit before revealing")]
+    NoPendingCommitment,
+    
+    #[msg("Reveal must be signed by the verifier that committed")]
+    VerifierMismatch,
+    
+    #[msg("Reveal must happen in a later slot than the commitment")]
+    RevealTooEarly,
+    
+    #[msg("Biometric commitment has expired - commit again")]
+    CommitmentExpired,
+    
+    #[msg("Revealed values do not match the commitment")]
+    CommitmentMismatch,
+    
+    #[msg("Signer is not a registered verifier")]
+    UnregisteredVerifier,
+    
+    #[msg("Verifier is already registered")]
+    VerifierAlreadyRegistered,
+    
+    #[msg("Verifier registry is full")]
+    VerifierRegistryFull,
+    
+    #[msg("NFT is soulbound with a locked transfer policy")]
+    TransferLocked,
+    
+    #[msg("Attester is not allowed to approve unlocks under this transfer policy")]
+    InvalidUnlockAttester,
+    
+    #[msg("No approved unlock for this transfer")]
+    UnlockNotApproved,
+    
+    #[msg("Approved unlock has expired - request a new one")]
+    UnlockExpired,
+    
+    #[msg("Quality weights must sum to 10000 bps with a non-zero target record count")]
+    InvalidQualityParams,
+    
+    #[msg("Emotion history is empty")]
+    EmptyEmotionHistory,
+    
+    #[msg("A token has already been minted for this NFT")]
+    TokenAlreadyMinted,
+    
+    #[msg("ZK proof or verifying key is malformed")]
+    InvalidZkProof,
+    
+    #[msg("Emotion batch is empty")]
+    EmptyEmotionBatch,
+    
+    #[msg("Emotion batch exceeds 32 records")]
+    EmotionBatchTooLarge,
+    
+    #[msg("Emotion timestamps must strictly increase")]
+    NonMonotonicTimestamps,
+    
+    #[msg("Biometric storage consent is required to mint")]
+    ConsentRequired,
+    
+    #[msg("Owner has revoked consent for this data")]
+    ConsentRevoked,
+    
+    #[msg("Compatibility pair must be passed with nft_a sorting before nft_b")]
+    UnorderedCompatibilityPair,
+    
+    #[msg("Compatibility was refreshed too recently")]
+    CompatibilityCooldown,
+    
+    #[msg("Device vendor is not registered")]
+    UnregisteredVendor,
+    
+    #[msg("Device vendor is already registered")]
+    VendorAlreadyRegistered,
+    
+    #[msg("Device is not registered")]
+    UnregisteredDevice,
+    
+    #[msg("Device is already registered")]
+    DeviceAlreadyRegistered,
+    
+    #[msg("Device registry is full")]
+    DeviceRegistryFull,
+    
+    #[msg("Device attestation requires both the device registry and instructions sysvar")]
+    IncompleteDeviceAttestation,
+    
+    #[msg("Missing or invalid ed25519 device signature")]
+    InvalidDeviceSignature,
+    
+    #[msg("Transferring a minted NFT requires its mint, token accounts, mint authority and token program")]
+    MissingTokenAccounts,
+    
+    #[msg("Token account does not hold this NFT's token for the expected owner")]
+    InvalidTokenAccount,
+    
+    #[msg("Valence must be within -1.0 - 1.0 and arousal and dominance within 0.0 - 1.0")]
+    VadOutOfRange,
+    
+    #[msg("Account is not a legacy NFTAccount")]
+    InvalidLegacyAccount,
+    
+    #[msg("Account already uses the current NFTAccount layout")]
+    NotLegacyAccount,
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::solana_program::alt_bn128::prelude::{alt_bn128_addition, alt_bn128_pairing};
+
+    /// BN254 G1 generator (1, 2)
+    fn g1_generator() -> [u8; 64] {
+        let mut point = [0u8; 64];
+        point[31] = 1;
+        point[63] = 2;
+        point
+    }
+
+    /// BN254 G2 generator in EIP-197 order (x_im, x_re, y_im, y_re)
+    fn g2_generator() -> Vec<u8> {
+        [
+            "198e9393920d483a7260bfb731fb5d25f1aa493335a9e71297e485b7aef312c2",
+            "1800deef121f1e76426a00665e5c4479674322d4f75edadd46debd5cd992f6ed",
+            "090689d0585ff075ec9e99ad690c3395bc4b313370b38ef355acdadcd122975b",
+            "12c85ea5db8c6deb4aab71808dcb408fe3d1e7690c43d37b4ce6cc0166fa7daa",
+        ]
+        .concat()
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).unwrap(), 16).unwrap())
+        .collect()
+    }
+
+    #[test]
+    fn negate_g1_subtracts_y_from_the_field_modulus() {
+        let negated = negate_g1(&g1_generator());
+
+        assert_eq!(negated[..32], g1_generator()[..32]);
+        assert_eq!(negated[32..63], BN254_FIELD_MODULUS[..31]);
+        assert_eq!(negated[63], BN254_FIELD_MODULUS[31] - 2);
+        assert_eq!(negate_g1(&negated), g1_generator());
+    }
+
+    #[test]
+    fn negate_g1_plus_point_is_identity() {
+        let point = g1_generator();
+        let sum = alt_bn128_addition(&[&negate_g1(&point)[..], &point[..]].concat()).unwrap();
+
+        assert_eq!(sum, vec![0u8; 64]);
+    }
+
+    #[test]
+    fn negate_g1_keeps_the_identity() {
+        assert_eq!(negate_g1(&[0u8; 64]), [0u8; 64]);
+    }
+
+    #[test]
+    fn pairing_of_negated_point_is_identity() {
+        let g2 = g2_generator();
+        let balanced = [&negate_g1(&g1_generator())[..], &g2[..], &g1_generator()[..], &g2[..]].concat();
+        let unbalanced = [&g1_generator()[..], &g2[..], &g1_generator()[..], &g2[..]].concat();
+
+        assert!(is_pairing_identity(&alt_bn128_pairing(&balanced).unwrap()));
+        assert!(!is_pairing_identity(&alt_bn128_pairing(&unbalanced).unwrap()));
+    }
+
+    #[test]
+    fn pairing_identity_rejects_other_encodings() {
+        let mut one = [0u8; 32];
+        one[31] = 1;
+
+        assert!(is_pairing_identity(&one));
+        assert!(!is_pairing_identity(&[0u8; 32]));
+        assert!(!is_pairing_identity(&one[1..]));
+        one[0] = 1;
+        assert!(!is_pairing_identity(&one));
+    }
+
+    #[test]
+    fn zk_public_inputs_round_trip_hash_owner_and_nonce() {
+        let hash = compute_biometric_hash(&[7u8; 32], &[9u8; 32]);
+        let owner = Pubkey::new_from_array(core::array::from_fn(|i| i as u8));
+        let inputs = zk_public_inputs(&hash, &owner, 0x0102_0304_0506_0708).unwrap();
+
+        for input in &inputs[..4] {
+            assert_eq!(input[..16], [0u8; 16]);
+        }
+        assert_eq!(to_hex(&[&inputs[0][16..], &inputs[1][16..]].concat()), hash);
+        assert_eq!([&inputs[2][16..], &inputs[3][16..]].concat(), owner.to_bytes());
+        assert_eq!(inputs[4][..24], [0u8; 24]);
+        assert_eq!(inputs[4][24..], [1, 2, 3, 4, 5, 6, 7, 8]);
+    }
+
+    /// ed25519 program data: header, offsets, public key, zeroed signature, message
+    fn ed25519_data(public_key: &Pubkey, message: &[u8]) -> Vec<u8> {
+        const PUBLIC_KEY_OFFSET: u16 = 16;
+        const SIGNATURE_OFFSET: u16 = PUBLIC_KEY_OFFSET + 32;
+        const MESSAGE_OFFSET: u16 = SIGNATURE_OFFSET + 64;
+
+        let mut data = vec![1, 0];
+        for value in [
+            SIGNATURE_OFFSET,
+            u16::MAX,
+            PUBLIC_KEY_OFFSET,
+            u16::MAX,
+            MESSAGE_OFFSET,
+            message.len() as u16,
+            u16::MAX,
+        ] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data.extend_from_slice(public_key.as_ref());
+        data.extend_from_slice(&[0u8; 64]);
+        data.extend_from_slice(message);
+        data
+    }
+
+    fn attestation_message() -> Vec<u8> {
+        let emotion = CompactEmotion { timestamp: 1_700_000_000, ..CompactEmotion::erased() };
+        device_attestation_message(&Pubkey::new_from_array([3u8; 32]), &emotion)
+    }
+
+    #[test]
+    fn parse_ed25519_signer_returns_the_device_key() {
+        let device = Pubkey::new_from_array([5u8; 32]);
+        let message = attestation_message();
+
+        assert_eq!(parse_ed25519_signer(&ed25519_data(&device, &message), &message).unwrap(), device);
+    }
+
+    #[test]
+    fn parse_ed25519_signer_rejects_offsets_outside_the_instruction() {
+        let message = attestation_message();
+        let valid = ed25519_data(&Pubkey::new_from_array([5u8; 32]), &message);
+
+        let mut public_key_past_end = valid.clone();
+        public_key_past_end[6..8].copy_from_slice(&(valid.len() as u16 - 16).to_le_bytes());
+        let mut message_past_end = valid.clone();
+        message_past_end[10..12].copy_from_slice(&(valid.len() as u16).to_le_bytes());
+        let mut message_too_long = valid.clone();
+        message_too_long[12..14].copy_from_slice(&(message.len() as u16 + 1).to_le_bytes());
+
+        for data in [&public_key_past_end, &message_past_end, &message_too_long, &valid[..10].to_vec()] {
+            assert_eq!(
+                parse_ed25519_signer(data, &message).unwrap_err(),
+                ErrorCode::InvalidDeviceSignature.into()
+            );
+        }
+    }
+
+    #[test]
+    fn parse_ed25519_signer_rejects_data_from_other_instructions() {
+        let message = attestation_message();
+        let valid = ed25519_data(&Pubkey::new_from_array([5u8; 32]), &message);
+
+        // signature, public key and message instruction indices
+        for index_at in [4, 8, 14] {
+            let mut data = valid.clone();
+            data[index_at..index_at + 2].copy_from_slice(&0u16.to_le_bytes());
+            assert_eq!(
+                parse_ed25519_signer(&data, &message).unwrap_err(),
+                ErrorCode::InvalidDeviceSignature.into()
+            );
+        }
+    }
+
+    #[test]
+    fn parse_ed25519_signer_rejects_message_mismatch() {
+        let message = attestation_message();
+        let data = ed25519_data(&Pubkey::new_from_array([5u8; 32]), &message);
+        let other_nft = device_attestation_message(&Pubkey::new_from_array([4u8; 32]), &CompactEmotion::erased());
+
+        assert_eq!(
+            parse_ed25519_signer(&data, &other_nft).unwrap_err(),
+            ErrorCode::InvalidDeviceSignature.into()
+        );
+        assert_eq!(
+            parse_ed25519_signer(&data, &message[1..]).unwrap_err(),
+            ErrorCode::InvalidDeviceSignature.into()
+        );
+    }
+
+    #[test]
+    fn parse_ed25519_signer_rejects_multiple_signatures() {
+        let message = attestation_message();
+        let mut data = ed25519_data(&Pubkey::new_from_array([5u8; 32]), &message);
+        data[0] = 2;
+
+        assert!(parse_ed25519_signer(&data, &message).is_err());
+    }
+
+    fn emotion(valence: f64, arousal: f64, dominance: f64, timestamp: i64) -> CompactEmotion {
+        CompactEmotion {
+            primary_emotion: Emotion::from_vad(valence, arousal, dominance),
+            confidence: u8::MAX,
+            valence: to_fixed(valence, -1.0),
+            arousal: to_fixed(arousal, 0.0),
+            dominance: to_fixed(dominance, 0.0),
+            timestamp,
+            device_attested: false,
+        }
+    }
+
+    #[test]
+    fn compatibility_of_identical_histories_is_full() {
+        let history: Vec<CompactEmotion> =
+            (0..5).map(|i| emotion(0.2 * i as f64 - 0.4, 0.1 * i as f64, 0.5, i)).collect();
+
+        assert_eq!(compatibility_score_bps(&history, &history), Some((BPS_DENOMINATOR, 5)));
+    }
+
+    #[test]
+    fn compatibility_of_opposite_histories_is_zero() {
+        let high = vec![emotion(1.0, 1.0, 1.0, 0); 3];
+        let low = vec![emotion(-1.0, 0.0, 0.0, 0); 3];
+
+        assert_eq!(compatibility_score_bps(&high, &low), Some((0, 3)));
+    }
+
+    #[test]
+    fn compatibility_pairs_the_newest_records_of_unequal_histories() {
+        let mut long = vec![emotion(-1.0, 0.0, 0.0, 0); 4];
+        long.extend(vec![emotion(0.5, 0.5, 0.5, 1); 2]);
+        let short = vec![emotion(0.5, 0.5, 0.5, 1); 2];
+
+        assert_eq!(compatibility_score_bps(&long, &short), Some((BPS_DENOMINATOR, 2)));
+        assert_eq!(compatibility_score_bps(&short, &long), Some((BPS_DENOMINATOR, 2)));
+    }
+
+    #[test]
+    fn compatibility_caps_samples_at_the_window() {
+        let history = vec![emotion(0.0, 0.5, 0.5, 0); COMPATIBILITY_WINDOW + 4];
+
+        assert_eq!(
+            compatibility_score_bps(&history, &history),
+            Some((BPS_DENOMINATOR, COMPATIBILITY_WINDOW as u8))
+        );
+    }
+
+    #[test]
+    fn compatibility_needs_both_histories() {
+        let history = vec![emotion(0.0, 0.5, 0.5, 0)];
+
+        assert_eq!(compatibility_score_bps(&history, &[]), None);
+        assert_eq!(compatibility_score_bps(&[], &history), None);
+    }
+
+    fn delta(valence: i8, arousal: i8, dominance: i8, confidence: i8) -> EmotionDelta {
+        EmotionDelta {
+            valence,
+            arousal,
+            dominance,
+            confidence,
+            elapsed_seconds: 30,
+            primary_emotion: None,
+        }
+    }
+
+    #[test]
+    fn apply_delta_moves_each_component_by_whole_steps() {
+        let base = CompactEmotion { confidence: 200, device_attested: true, ..emotion(0.0, 0.5, 0.5, 100) };
+        let applied = base.apply_delta(&delta(10, -20, 5, -50));
+
+        assert_eq!(applied.valence, 10 * DELTA_STEP);
+        assert_eq!(applied.arousal, to_fixed(0.5, 0.0) - 20 * DELTA_STEP);
+        assert_eq!(applied.dominance, to_fixed(0.5, 0.0) + 5 * DELTA_STEP);
+        assert_eq!(applied.confidence, 150);
+        assert_eq!(applied.timestamp, 130);
+        assert_eq!(applied.primary_emotion, base.primary_emotion);
+        assert!(!applied.device_attested);
+    }
+
+    #[test]
+    fn apply_delta_clamps_each_axis_to_its_range() {
+        let base = emotion(0.5, 0.5, 0.5, 0);
+
+        // -128 steps is -1.28, which only clamps on the 0.0 - 1.0 axes
+        let lowered = base.apply_delta(&delta(-128, -128, -128, -128));
+        assert_eq!(lowered.valence, to_fixed(-0.78, -1.0));
+        assert_eq!(lowered.arousal, 0);
+        assert_eq!(lowered.dominance, 0);
+        assert_eq!(lowered.confidence, 127);
+
+        let raised = lowered.apply_delta(&delta(127, 127, 127, 127)).apply_delta(&delta(127, 0, 0, 127));
+        assert_eq!(lowered.apply_delta(&delta(-128, 0, 0, 0)).valence, -(VAD_SCALE as i16));
+        assert_eq!(raised.valence, VAD_SCALE as i16);
+        assert_eq!(raised.arousal, VAD_SCALE as i16);
+        assert_eq!(raised.dominance, VAD_SCALE as i16);
+        assert_eq!(raised.confidence, u8::MAX);
+    }
+
+    #[test]
+    fn apply_delta_can_replace_the_primary_emotion() {
+        let base = emotion(0.5, 0.8, 0.5, 0);
+        let applied = base.apply_delta(&EmotionDelta {
+            primary_emotion: Some(Emotion::Other(42)),
+            ..delta(0, 0, 0, 0)
+        });
+
+        assert_eq!(applied.primary_emotion, Emotion::Other(42));
+        assert_eq!((applied.valence, applied.arousal, applied.dominance), (base.valence, base.arousal, base.dominance));
+        assert_eq!(applied.confidence, base.confidence);
+    }
+
+    fn quality_params() -> QualityParams {
+        QualityParams {
+            confidence_weight_bps: 4_000,
+            consistency_weight_bps: 4_000,
+            volume_weight_bps: 2_000,
+            target_record_count: 10,
+        }
+    }
+
+    #[test]
+    fn quality_of_empty_history_is_zero() {
+        assert_eq!(compute_quality_score(&[], &quality_params()), 0.0);
+    }
+
+    #[test]
+    fn quality_of_consistent_confident_history_is_full() {
+        let history = vec![emotion(0.3, 0.6, 0.4, 0); 10];
+
+        assert!((compute_quality_score(&history, &quality_params()) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn quality_penalizes_inconsistent_history() {
+        let history: Vec<CompactEmotion> = (0..10)
+            .map(|i| if i % 2 == 0 { emotion(1.0, 1.0, 1.0, i) } else { emotion(-1.0, 0.0, 0.0, i) })
+            .collect();
+
+        // Each record sits 2/3 from the mean on average, leaving 1/3 consistency
+        let expected = 0.4 + 0.4 / 3.0 + 0.2;
+        assert!((compute_quality_score(&history, &quality_params()) - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn quality_of_zero_confidence_history_only_credits_volume() {
+        let history = vec![CompactEmotion { confidence: 0, ..emotion(0.3, 0.6, 0.4, 0) }; 5];
+
+        assert!((compute_quality_score(&history, &quality_params()) - 0.1).abs() < 1e-9);
+    }
+
+    #[test]
+    fn quality_volume_credit_caps_at_target() {
+        let history = vec![emotion(0.3, 0.6, 0.4, 0); 40];
+
+        assert!((compute_quality_score(&history, &quality_params()) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn revealed_digest_differs_per_salt_and_matches_enrollment() {
+        let template = b"enrolled template";
+        let (salt_a, salt_b) = ([1u8; 32], [2u8; 32]);
+        let digest_a = compute_salted_digest(&salt_a, template);
+        let digest_b = compute_salted_digest(&salt_b, template);
+
+        assert_ne!(digest_a, digest_b);
+        assert_ne!(compute_biometric_hash(&salt_a, &digest_a), to_hex(&digest_a));
+        assert_eq!(
+            compute_biometric_hash(&salt_a, &digest_a),
+            to_hex(&compute_salted_digest(&salt_a, &compute_salted_digest(&salt_a, template)))
+        );
+        assert_ne!(compute_biometric_hash(&salt_a, &digest_b), compute_biometric_hash(&salt_a, &digest_a));
+    }
+
+    #[test]
+    fn compact_emotion_range_matches_each_axis() {
+        let scale = VAD_SCALE as i16;
+
+        assert!(emotion(-1.0, 0.0, 0.0, 0).is_in_range());
+        assert!(emotion(1.0, 1.0, 1.0, 0).is_in_range());
+        assert!(!CompactEmotion { valence: -scale - 1, ..emotion(0.0, 0.5, 0.5, 0) }.is_in_range());
+        assert!(!CompactEmotion { valence: scale + 1, ..emotion(0.0, 0.5, 0.5, 0) }.is_in_range());
+        assert!(!CompactEmotion { arousal: -1, ..emotion(0.0, 0.5, 0.5, 0) }.is_in_range());
+        assert!(!CompactEmotion { dominance: -1, ..emotion(0.0, 0.5, 0.5, 0) }.is_in_range());
+        assert!(!CompactEmotion { arousal: i16::MAX, ..emotion(0.0, 0.5, 0.5, 0) }.is_in_range());
+    }
+
+    fn legacy_emotion(label: &str, timestamp: i64) -> LegacyEmotionData {
+        LegacyEmotionData {
+            primary_emotion: label.to_string(),
+            confidence: 0.9,
+            secondary_emotions: vec![LegacySecondaryEmotion { emotion: "calm".to_string(), score: 0.2 }],
+            valence: 0.6,
+            arousal: 0.3,
+            dominance: 1.5,
+            timestamp,
+        }
+    }
+
+    fn legacy_account(records: i64) -> LegacyNFTAccount {
+        LegacyNFTAccount {
+            owner: Pubkey::new_from_array([8u8; 32]),
+            emotion_data: legacy_emotion("happy", records),
+            quality_score: 0.85,
+            biometric_hash: "f".repeat(64),
+            is_verified: true,
+            created_at: 1_600_000_000,
+            emotion_history: (1..=records)
+                .map(|i| legacy_emotion(if i % 2 == 0 { "happy" } else { "mystery" }, i))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn legacy_accounts_do_not_decode_as_nft_accounts() {
+        let mut data = NFTAccount::DISCRIMINATOR.to_vec();
+        legacy_account(3).serialize(&mut data).unwrap();
+
+        assert!(NFTAccount::try_deserialize(&mut &data[..]).is_err());
+        assert_eq!(LegacyNFTAccount::deserialize(&mut &data[8..]).unwrap().emotion_history.len(), 3);
+    }
+
+    #[test]
+    fn migrate_legacy_layout_keeps_recent_history_on_opt_in() {
+        let migrated = migrate_legacy_layout(legacy_account(MAX_EMOTION_HISTORY as i64 + 5), true);
+
+        assert_eq!(migrated.emotion_history.len(), MAX_EMOTION_HISTORY);
+        assert_eq!(migrated.emotion_history[0].timestamp, 6);
+        assert_eq!(migrated.emotion_data, *migrated.emotion_history.last().unwrap());
+        // Known labels map directly, unknown ones fall back to the VAD point
+        assert_eq!(migrated.emotion_history[0].primary_emotion, Emotion::Joy);
+        assert_eq!(migrated.emotion_history[1].primary_emotion, Emotion::from_vad(0.6, 0.3, 1.5));
+        assert_eq!(migrated.emotion_data.dominance, VAD_SCALE as i16);
+        assert_eq!(migrated.consent_flags, CONSENT_EMOTION_HISTORY);
+        assert_eq!(migrated.quality_score, 0.85);
+        assert_eq!(migrated.created_at, 1_600_000_000);
+
+        let mut data = Vec::new();
+        migrated.try_serialize(&mut data).unwrap();
+        assert!(data.len() <= NFTAccount::SPACE);
+        assert!(NFTAccount::try_deserialize(&mut &data[..]).is_ok());
+    }
+
+    #[test]
+    fn migrate_legacy_layout_drops_the_unsalted_hash_and_unconsented_history() {
+        let migrated = migrate_legacy_layout(legacy_account(4), false);
+
+        assert!(migrated.biometric_hash.is_empty());
+        assert_eq!(migrated.hash_version, HASH_VERSION_UNENROLLED);
+        assert!(!migrated.is_verified);
+        assert!(migrated.emotion_history.is_empty());
+        assert_eq!(migrated.emotion_data, CompactEmotion::erased());
+        assert_eq!(migrated.consent_flags, 0);
+        assert_eq!(migrated.transfer_policy, TransferPolicy::Locked);
+    }
+
+    #[test]
+    fn zk_public_inputs_reject_malformed_hash() {
+        let owner = Pubkey::default();
+
+        assert!(zk_public_inputs("", &owner, 0).is_none());
+        assert!(zk_public_inputs(&"A".repeat(64), &owner, 0).is_none());
+        assert!(zk_public_inputs(&"a".repeat(63), &owner, 0).is_none());
+    }
+}
@@ -40,6 +40,84 @@ const idl = {
         {
           "name": "biometricHash",
           "type": "string"
+        },
+        {
+          "name": "biometricSalt",
+          "type": {
+            "array": ["u8", 32]
+          }
+        },
+        {
+          "name": "transferPolicy",
+          "type": {
+            "defined": "TransferPolicy"
+          }
+        },
+        {
+          "name": "consentFlags",
+          "type": "u8"
+        }
+      ]
+    },
+    {
+      "name": "commitBiometric",
+      "accounts": [
+        {
+          "name": "nftAccount",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "verifierRegistry",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "verifier",
+          "isMut": false,
+          "isSigner": true
+        }
+      ],
+      "args": [
+        {
+          "name": "commitment",
+          "type": {
+            "array": ["u8", 32]
+          }
+        }
+      ]
+    },
+    {
+      "name": "verifyBiometric",
+      "accounts": [
+        {
+          "name": "nftAccount",
+          "isMut": true,
+          "isSigner": false
+        },
+        {
+          "name": "verifierRegistry",
+          "isMut": false,
+          "isSigner": false
+        },
+        {
+          "name": "verifier",
+          "isMut": false,
+          "isSigner": true
+        }
+      ],
+      "args": [
+        {
+          "name": "saltedDigest",
+          "type": {
+            "array": ["u8", 32]
+          }
+        },
+        {
+          "name": "nonce",
+          "type": {
+            "array": ["u8", 32]
+          }
         }
       ]
     }
@@ -86,6 +164,24 @@ const idl = {
       "type": {
         "kind": "struct",
         "fields": [
+          {
+            "name": "primaryEmotion",
+            "type": {
+              "defined": "Emotion"
+            }
+          },
+          {
+            "name": "confidence",
+            "type": "f64"
+          },
+          {
+            "name": "secondaryEmotions",
+            "type": {
+              "vec": {
+                "defined": "SecondaryEmotion"
+              }
+            }
+          },
           {
             "name": "valence",
             "type": "f64"
@@ -104,6 +200,52 @@ const idl = {
           }
         ]
       }
+    },
+    {
+      "name": "SecondaryEmotion",
+      "type": {
+        "kind": "struct",
+        "fields": [
+          {
+            "name": "emotion",
+            "type": {
+              "defined": "Emotion"
+            }
+          },
+          {
+            "name": "score",
+            "type": "f64"
+          }
+        ]
+      }
+    },
+    {
+      "name": "Emotion",
+      "type": {
+        "kind": "enum",
+        "variants": [
+          { "name": "Neutral" },
+          { "name": "Joy" },
+          { "name": "Sadness" },
+          { "name": "Anger" },
+          { "name": "Fear" },
+          { "name": "Surprise" },
+          { "name": "Disgust" },
+          { "name": "Trust" },
+          { "name": "Other", "fields": ["u8"] }
+        ]
+      }
+    },
+    {
+      "name": "TransferPolicy",
+      "type": {
+        "kind": "enum",
+        "variants": [
+          { "name": "Locked" },
+          { "name": "UnlockableByDAO" },
+          { "name": "CompromisedRecovery" }
+        ]
+      }
     }
   ],
   "errors": [
@@ -120,11 +262,19 @@ const idl = {
 
 const PROGRAM_ID = new PublicKey('3BRGASWgfiPsxwVQq2W6JKLuWvZRBRSd3gkgfeDt9yoA');
 
+// Consent bits matching the program's CONSENT_* constants
+export const CONSENT_BIOMETRIC_STORAGE = 1 << 0;
+export const CONSENT_EMOTION_HISTORY = 1 << 1;
+export const CONSENT_ALL = CONSENT_BIOMETRIC_STORAGE | CONSENT_EMOTION_HISTORY;
+
+export type TransferPolicy = 'locked' | 'unlockableByDao' | 'compromisedRecovery';
+
 interface EmotionData {
   valence: number;
   arousal: number;
   dominance: number;
-  timestamp?: number;
+  confidence?: number;
+  timestamp?: number; // Unix seconds
 }
 
 interface NFTAccount {
@@ -146,26 +296,34 @@ export class BiometricNFTClient {
     this.program = new Program(idl as any, PROGRAM_ID, provider);
   }
 
-  // Initialize a new biometric NFT
+  // Initialize a new biometric NFT, enrolling SHA-256(salt || SHA-256(salt || biometricData))
   async initializeNFT(
     payer: PublicKey,
     emotionData: EmotionData,
     qualityScore: number,
-    biometricHash: string
-  ): Promise<{ nftAccount: PublicKey; transactionSignature: string }> {
+    biometricData: string,
+    transferPolicy: TransferPolicy = 'locked',
+    consentFlags: number = CONSENT_ALL
+  ): Promise<{ nftAccount: PublicKey; transactionSignature: string; biometricSalt: Uint8Array }> {
     try {
       // Generate a new NFT account address
       const nftAccount = web3.Keypair.generate();
-      
-      // Add timestamp if not provided
-      const emotionDataWithTimestamp = {
-        ...emotionData,
-        timestamp: emotionData.timestamp || Date.now()
-      };
+
+      // The salt is public on-chain; verifiers need it to build the salted digest
+      const biometricSalt = crypto.getRandomValues(new Uint8Array(32));
+      const saltedDigest = await this.saltedDigest(biometricSalt, biometricData);
+      const biometricHash = toHex(await this.sha256(new Uint8Array([...biometricSalt, ...saltedDigest])));
 
       // Create the transaction
       const tx = await this.program.methods
-        .initializeNft(emotionDataWithTimestamp, qualityScore, biometricHash)
+        .initializeNft(
+          toProgramEmotion(emotionData),
+          qualityScore,
+          biometricHash,
+          Array.from(biometricSalt),
+          { [transferPolicy]: {} },
+          consentFlags
+        )
         .accounts({
           nftAccount: nftAccount.publicKey,
           payer: payer,
@@ -176,7 +334,8 @@ export class BiometricNFTClient {
 
       return {
         nftAccount: nftAccount.publicKey,
-        transactionSignature: tx
+        transactionSignature: tx,
+        biometricSalt
       };
     } catch (error) {
       console.error('Error initializing NFT:', error);
@@ -184,17 +343,40 @@ export class BiometricNFTClient {
     }
   }
 
-  // Verify biometric data
+  // Verify biometric data with a commit-reveal so the raw template never leaves the client
   async verifyBiometric(
     nftAccount: PublicKey,
     verifier: PublicKey,
-    biometricData: string
+    biometricData: string,
+    biometricSalt: Uint8Array
   ): Promise<string> {
     try {
+      const [verifierRegistry] = PublicKey.findProgramAddressSync(
+        [new TextEncoder().encode('verifier_registry')],
+        PROGRAM_ID
+      );
+
+      // The revealed digest is public, so it is salted per NFT; the program checks
+      // SHA-256(salt || saltedDigest) against the enrolled hash
+      const saltedDigest = await this.saltedDigest(biometricSalt, biometricData);
+      const nonce = crypto.getRandomValues(new Uint8Array(32));
+      const commitment = await this.sha256(new Uint8Array([...saltedDigest, ...nonce]));
+
+      await this.program.methods
+        .commitBiometric(Array.from(commitment))
+        .accounts({
+          nftAccount: nftAccount,
+          verifierRegistry: verifierRegistry,
+          verifier: verifier,
+        })
+        .rpc({ commitment: 'confirmed' });
+
+      // The reveal must land in a later slot than the confirmed commitment
       const tx = await this.program.methods
-        .verifyBiometric(biometricData)
+        .verifyBiometric(Array.from(saltedDigest), Array.from(nonce))
         .accounts({
           nftAccount: nftAccount,
+          verifierRegistry: verifierRegistry,
           verifier: verifier,
         })
         .rpc();
@@ -206,6 +388,15 @@ export class BiometricNFTClient {
     }
   }
 
+  private async sha256(data: Uint8Array): Promise<Uint8Array> {
+    return new Uint8Array(await crypto.subtle.digest('SHA-256', data));
+  }
+
+  // SHA-256(salt || biometricData), the per-NFT value revealed during verification
+  private async saltedDigest(salt: Uint8Array, biometricData: string): Promise<Uint8Array> {
+    return this.sha256(new Uint8Array([...salt, ...new TextEncoder().encode(biometricData)]));
+  }
+
   // Update emotion data
   async updateEmotion(
     nftAccount: PublicKey,
@@ -213,13 +404,8 @@ export class BiometricNFTClient {
     newEmotionData: EmotionData
   ): Promise<string> {
     try {
-      const emotionDataWithTimestamp = {
-        ...newEmotionData,
-        timestamp: newEmotionData.timestamp || Date.now()
-      };
-
       const tx = await this.program.methods
-        .updateEmotion(emotionDataWithTimestamp)
+        .updateEmotion(toProgramEmotion(newEmotionData))
         .accounts({
           nftAccount: nftAccount,
           owner: owner,
@@ -348,6 +534,42 @@ export class BiometricNFTClient {
   }
 }
 
+// Current time in Unix seconds, the unit the program compares timestamps in
+export function unixSeconds(): number {
+  return Math.floor(Date.now() / 1000);
+}
+
+function toHex(bytes: Uint8Array): string {
+  return Array.from(bytes).map(b => b.toString(16).padStart(2, '0')).join('');
+}
+
+// Mirror of the program's Emotion::from_vad categorization
+function emotionFromVad(valence: number, arousal: number, dominance: number): string {
+  if (valence > 0.1) {
+    return arousal >= 0.5 ? 'joy' : 'trust';
+  }
+  if (valence < -0.1) {
+    if (arousal >= 0.5) {
+      return dominance >= 0.5 ? 'anger' : 'fear';
+    }
+    return dominance >= 0.5 ? 'disgust' : 'sadness';
+  }
+  return arousal >= 0.7 ? 'surprise' : 'neutral';
+}
+
+// Build the program's EmotionData argument, defaulting the timestamp to now
+function toProgramEmotion(emotionData: EmotionData) {
+  return {
+    primaryEmotion: { [emotionFromVad(emotionData.valence, emotionData.arousal, emotionData.dominance)]: {} },
+    confidence: emotionData.confidence ?? 1.0,
+    secondaryEmotions: [],
+    valence: emotionData.valence,
+    arousal: emotionData.arousal,
+    dominance: emotionData.dominance,
+    timestamp: new BN(emotionData.timestamp ?? unixSeconds()),
+  };
+}
+
 // Helper function to create AnchorProvider
 export function createAnchorProvider(connection: Connection, wallet: any): AnchorProvider {
   const provider = new AnchorProvider(